@@ -16,8 +16,5 @@ fn main() {
   if app.is_present("debug") { env::set_var("OUTPUT_DEBUG_ENABLED","true"); }
 
   // processess the arguement matches.
-  match interface::process(&app) {
-    Err(error) => { println!("{}",error); }
-    Ok(_) => { }
-  }
+  if let Err(error) = interface::process(&app) { println!("{}",error); }
 }
\ No newline at end of file
@@ -1,10 +1,15 @@
 use clap;
-use ansi_term::Colour::Blue;
-use std::path::PathBuf;
-use std::env;
+use ansi_term::Colour::{Blue,Red};
+use regex;
+use serde_json;
+use std::path::{Path,PathBuf};
 
 use lpsettings;
 use local;
+use library;
+use library::lualibdef::{Profile,FeatureRequest};
+use processing;
+use processing::settings::Settings;
 
 pub fn process(matches : &clap::ArgMatches) -> Result<(),&'static str> {
   //! process function to be used with [CLAP.RS](https://clap.rs/)'s `.get_matches()`.
@@ -22,9 +27,16 @@ pub fn process(matches : &clap::ArgMatches) -> Result<(),&'static str> {
   // success!
 
   match matches.subcommand() {
-    ("compile", Some(sub_m)) => { return process_compile(&sub_m); }
-    ("install", Some(sub_m)) => { return process_install(&sub_m); }
-    _ => { output_error!("Not matches found"); return Err("error"); }
+    ("compile", Some(sub_m)) => { process_compile(sub_m) }
+    ("install", Some(sub_m)) => { process_install(sub_m) }
+    ("tree", Some(sub_m)) => { process_tree(sub_m) }
+    ("list", Some(sub_m)) => { process_list(sub_m) }
+    ("search", Some(sub_m)) => { process_search(sub_m) }
+    ("info", Some(sub_m)) => { process_info(sub_m) }
+    ("test", Some(sub_m)) => { process_test(sub_m) }
+    ("check", Some(sub_m)) => { process_check(sub_m) }
+    ("release", Some(sub_m)) => { process_release(sub_m) }
+    _ => { output_error!("Not matches found"); Err("error") }
   }
   
 }
@@ -32,11 +44,9 @@ pub fn process(matches : &clap::ArgMatches) -> Result<(),&'static str> {
 fn process_install(matches : &clap::ArgMatches) -> Result<(),&'static str> {
 
   match matches.subcommand() {
-    ("add", Some(sub_m)) => { process_install_add(&sub_m); }
-    _ => { process_install_none(&matches); }
+    ("add", Some(sub_m)) => { process_install_add(sub_m) }
+    _ => { process_install_none(matches) }
   }
-
-  Ok ( () )
 }
 
 fn process_install_none(matches : &clap::ArgMatches) -> Result<(),&'static str> {
@@ -46,7 +56,7 @@ fn process_install_none(matches : &clap::ArgMatches) -> Result<(),&'static str>
     if let Some(game_folder) = lpsettings::get_value("project.game-folder") { path.push(game_folder); }
     path.push(lpsettings::get_value_or("project.library-compile-path","lib"));
 
-    local::install::from_toml(&path);
+    local::install::from_toml(&path,&profile_name(matches),job_count(matches));
 
   }
   Ok ( () )
@@ -56,18 +66,19 @@ fn process_install_add(matches : &clap::ArgMatches) -> Result<(),&'static str> {
   if let Some(libraries) = matches.values_of("LIBRARY") {
     for lib in libraries {
       
-      let mut vec : Vec<&str> = lib.split("$").collect();
+      let vec : Vec<&str> = lib.split("$").collect();
       let var : Option<String> = if vec.len() > 1 { Some(vec[1].to_string()) } else { None };
 
-      let mut vec_2 : Vec<&str>  = vec[0].split(":").collect();
-      let version : Option<String> = if vec_2.len() > 1 { Some(vec_2[1].to_string()) } else { Some("latest".to_string()) };
+      let vec_2 : Vec<&str>  = vec[0].split(":").collect();
+      let version : String = if vec_2.len() > 1 { vec_2[1].to_string() } else { "latest".to_string() };
       let name : String = vec_2[0].to_string();
 
-      if var.is_some() {
-        lpsettings::set_value_local(&format!("project.libraries.{}.version",&name),&version.unwrap());
-        lpsettings::set_value_local(&format!("project.libraries.{}.var",&name),&var.unwrap());
-      } else {
-        lpsettings::set_value_local(&format!("project.libraries.{}",&name),&version.unwrap());
+      match var {
+        Some(var) => {
+          lpsettings::set_value_local(&format!("project.libraries.{}.version",&name),&version);
+          lpsettings::set_value_local(&format!("project.libraries.{}.var",&name),&var);
+        }
+        None => { lpsettings::set_value_local(&format!("project.libraries.{}",&name),&version); }
       }
     }
   }
@@ -76,22 +87,35 @@ fn process_install_add(matches : &clap::ArgMatches) -> Result<(),&'static str> {
 
 fn process_compile(matches : &clap::ArgMatches) -> Result<(),&'static str> {
 
+  let library_path : PathBuf = if let Some(lib) = matches.value_of("PATH") { PathBuf::from(lib) } else { PathBuf::from(".") };
+
+  if let Some(members) = workspace_members(&library_path) {
+    // every member would get the same name
+    if matches.is_present("compiled-name") { return Err("--compiled-name can't be used with a workspace."); }
+    // members that depend on each other would never finish compiling
+    if let Some(workspace) = library::workspace::load(&library_path) {
+      let cycles : Vec<Vec<String>> = workspace.member_cycles();
+      for cycle in cycles.iter() { output_error!("Dependency cycle: {}",Red.paint(cycle.join(" -> "))); }
+      if !cycles.is_empty() { return Err("Workspace members depend on each other."); }
+    }
+    return for_each_member(&members,|member| compile_library(matches,member));
+  }
+  compile_library(matches,&library_path)
+}
 
-  if matches.is_present("name-with-version") || lpsettings::get_value_or("lmake.name-with-version","false") == "true" { env::set_var("LMAKE_COMPILE_WITH_VERSION_IN_NAME","true"); }
-  if matches.is_present("remove-comments") || lpsettings::get_value_or("lmake.remove-comments","false") == "true" { env::set_var("LMAKE_REMOVE_COMMENTS","true"); } 
+fn compile_library(matches : &clap::ArgMatches, library_path : &PathBuf) -> Result<(),&'static str> {
 
-  if let Some(new_name) = matches.value_of("compiled-name") { env::set_var("LMAKE_COMPILE_NAME",new_name); }
+  let settings : Settings = select_profile(matches,library_path)?;
 
-  let library_path : PathBuf = if let Some(lib) = matches.value_of("PATH") { PathBuf::from(lib) } else { PathBuf::from(".") };
   output_debug!("using {} as the library path",Blue.paint(library_path.display().to_string()));
   match library_path.exists() {
     true => { output_debug!("Path exists.");
-      let mut destination_path = library_path.clone();
-      destination_path.push(lpsettings::get_value_or("lmake.compile-path","bin"));
+      let destination_path : PathBuf = destination(library_path,&settings.output());
 
-      match super::compile(&library_path, &destination_path, false, &None) {
-        Err(error) => { output_error!("Error compiling: {}",error.to_string()); }
-        Ok(path) => { println!("Successfully compiled: {}",Blue.paint(path.display().to_string())); }
+      let target : Option<String> = matches.value_of("target").map(|target| target.to_string());
+      match super::compile(library_path, &destination_path, false, &None, &feature_request(matches), &target, &settings) {
+        Err(error) => { output_error!("Error compiling: {}",error.to_string()); return Err(error); }
+        Ok(paths) => { for path in paths { println!("Successfully compiled: {}",Blue.paint(path.display().to_string())); } }
       }
     }
     false => { 
@@ -100,10 +124,234 @@ fn process_compile(matches : &clap::ArgMatches) -> Result<(),&'static str> {
     }
   }
 
-  
+  Ok (())
+}
 
+fn destination(library_path : &Path, output : &Option<String>) -> PathBuf {
+  //! where the library is compiled to, the profile's `output`, then the workspace's `output`, then the settings.
+  if let Some(ref output) = *output {
+    let mut path : PathBuf = library_path.to_path_buf();
+    path.push(output);
+    return path;
+  }
+  if let Some(path) = library::workspace::containing(library_path).and_then(|workspace| workspace.output_path()) { return path; }
 
-  Ok (())
+  let mut path : PathBuf = library_path.to_path_buf();
+  path.push(lpsettings::get_value_or("lmake.compile-path","bin"));
+  path
+}
+
+fn workspace_members(path : &PathBuf) -> Option<Vec<PathBuf>> {
+  //! the members when `path` is the folder of a workspace.
+  library::workspace::load(path).map(|workspace| workspace.member_paths())
+}
+
+fn for_each_member<F>(members : &[PathBuf], mut action : F) -> Result<(),&'static str> where F : FnMut(&PathBuf) -> Result<(),&'static str> {
+  //! runs the command for every member of the workspace, even if one of them fails.
+  let mut failed : Vec<String> = Vec::new();
+  for member in members.iter() {
+    output_println!("{}",Blue.paint(member.display().to_string()));
+    if let Err(error) = action(member) {
+      output_error!("{}: {}",Red.paint(member.display().to_string()),error);
+      failed.push(member.display().to_string());
+    }
+  }
+
+  match failed.len() {
+    0 => { Ok(()) }
+    _ => { output_error!("Failed in {} of {} members: {}",failed.len(),members.len(),Red.paint(failed.join(", "))); Err("Workspace member failed.") }
+  }
+}
+
+fn feature_request(matches : &clap::ArgMatches) -> FeatureRequest {
+  //! the features from `--features`, which can be given more than once.
+  let features : Vec<&str> = if let Some(features) = matches.values_of("features") { features.collect() } else { Vec::new() };
+  FeatureRequest::new(&features.join(","),matches.is_present("no-default-features"))
+}
+
+fn job_count(matches : &clap::ArgMatches) -> Option<usize> {
+  //! `--jobs`, if it was given, clap already made sure it is a number.
+  matches.value_of("jobs").and_then(|jobs| jobs.parse().ok())
+}
+
+fn validate_job_count(jobs : String) -> Result<(),String> {
+  match jobs.parse::<usize>() {
+    Ok(count) if count > 0 => { Ok(()) }
+    _ => { Err(format!("'{}' isn't a number of jobs, it has to be 1 or more",jobs)) }
+  }
+}
+
+fn profile_name(matches : &clap::ArgMatches) -> String {
+  matches.value_of("profile").map(|name| name.to_string()).unwrap_or(lpsettings::get_value_or("lmake.profile","dev"))
+}
+
+fn select_profile(matches : &clap::ArgMatches, library_path : &Path) -> Result<Settings,&'static str> {
+  //! the settings with the build profile from `--profile` (or `dev`) and the switches that were given.
+  let name : String = profile_name(matches);
+  let switch = |name : &str| -> Option<bool> { if matches.is_present(name) { Some(true) } else { None } };
+  let switches : Profile = Profile {
+    remove_comments : switch("remove-comments"),
+    minify : switch("minify"),
+    debug_build : switch("debug-build"),
+    name_with_version : switch("name-with-version"),
+    compiled_name : matches.value_of("compiled-name").map(|name| name.to_string()),
+    .. Profile::default()
+  };
+
+  match Settings::load(library_path,&name,switches) {
+    Ok(mut settings) => {
+      output_debug!("using the {} profile",Blue.paint(name));
+      settings.command_defines = matches.values_of("define").map(|defines| defines.map(|define| define.to_string()).collect()).unwrap_or_default();
+      settings.allow_unresolved = matches.is_present("allow-unresolved") || lpsettings::get_value_or("lmake.allow-unresolved","false") == "true";
+      settings.deny_globals = matches.is_present("deny-globals") || lpsettings::get_value_or("lmake.deny-globals","false") == "true";
+      settings.jobs = job_count(matches);
+      Ok(settings)
+    }
+    Err(error) => { output_error!("Cannot use the profile: {}",Red.paint(error)); Err("Unknown profile.") }
+  }
+}
+
+fn process_tree(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+
+  let library_path : PathBuf = if let Some(lib) = matches.value_of("PATH") { PathBuf::from(lib) } else { PathBuf::from(".") };
+  if let Some(members) = workspace_members(&library_path) { return for_each_member(&members,|member| tree_library(matches,member)); }
+  tree_library(matches,&library_path)
+}
+
+fn tree_library(matches : &clap::ArgMatches, library_path : &Path) -> Result<(),&'static str> {
+  output_debug!("using {} as the library path",Blue.paint(library_path.display().to_string()));
+
+  if !processing::compile::validate_lualib_path(library_path) { return Err("Not a valid library"); }
+  // nothing in the tree depends on the profile, but a profile that doesn't exist is still a mistake
+  select_profile(matches,library_path)?;
+
+  match library::luafile::get_lualib_settings(library_path) {
+    None => { Err("Error loading library definition.") }
+    Some(mut definition) => {
+      if let Err(error) = definition.with_features(&feature_request(matches)) { output_error!("{}",Red.paint(error)); return Err("Unknown feature."); }
+      let tree = processing::tree::build(library_path,&definition);
+
+      match matches.value_of("format") {
+        Some("json") => { println!("{}",processing::tree::to_json(&tree)); }
+        Some("dot") => { println!("{}",processing::tree::to_dot(&tree)); }
+        _ => { println!("{}",processing::tree::to_text(&tree)); }
+      }
+
+      Ok(())
+    }
+  }
+}
+
+fn process_list(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+  match lpsettings::get_value("library.local-folder") {
+    None => {
+      output_error!("No local library path set, please set value {} in order to use.",Red.paint("library.local-folder"));
+      Err("No local library folder.")
+    }
+    Some(folder) => {
+      let listings = local::library::list_local_libraries(&PathBuf::from(folder));
+      print_listings(&listings.iter().collect(),matches.value_of("format"));
+      Ok(())
+    }
+  }
+}
+
+fn process_search(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+  let pattern : &str = matches.value_of("PATTERN").unwrap();
+  // falls back to searching for the literal text if the pattern isn't a valid regex.
+  let re = match regex::Regex::new(&format!("(?i){}",pattern)) {
+    Ok(re) => { re }
+    Err(_) => { regex::Regex::new(&format!("(?i){}",regex::escape(pattern))).unwrap() }
+  };
+
+  match lpsettings::get_value("library.local-folder") {
+    None => {
+      output_error!("No local library path set, please set value {} in order to use.",Red.paint("library.local-folder"));
+      Err("No local library folder.")
+    }
+    Some(folder) => {
+      let listings = local::library::list_local_libraries(&PathBuf::from(folder));
+      print_listings(&listings.iter().filter(|listing| listing.matches(&re)).collect(),matches.value_of("format"));
+      Ok(())
+    }
+  }
+}
+
+fn process_info(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+  let library : &str = matches.value_of("LIBRARY").unwrap();
+
+  // can either be a path to a library, or the name of a library in the local library folder.
+  let path : PathBuf = if processing::compile::validate_lualib_path_quiet(&PathBuf::from(library)) { PathBuf::from(library) } else {
+    match processing::compile::get_library_path_git(library) {
+      None => { 
+        output_error!("Cannot find library {}.",Red.paint(library.to_string()));
+        return Err("Library not found.");
+      }
+      Some(path) => { path }
+    }
+  };
+
+  match library::luafile::get_lualib_settings(&path) {
+    None => { Err("Error loading library definition.") }
+    Some(definition) => {
+      println!("{}",local::info::describe(&path,&definition));
+      Ok(())
+    }
+  }
+}
+
+fn process_test(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+  let library_path : PathBuf = if let Some(lib) = matches.value_of("PATH") { PathBuf::from(lib) } else { PathBuf::from(".") };
+  if let Some(members) = workspace_members(&library_path) { return for_each_member(&members,|member| test_library(matches,member)); }
+  test_library(matches,&library_path)
+}
+
+fn test_library(matches : &clap::ArgMatches, library_path : &PathBuf) -> Result<(),&'static str> {
+  let settings : Settings = select_profile(matches,library_path)?;
+  let destination_path : PathBuf = destination(library_path,&settings.output());
+
+  super::test(library_path,&destination_path,&settings)?;
+  println!("Tests passed for {}",Blue.paint(library_path.display().to_string()));
+  Ok(())
+}
+
+fn process_check(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+  let library_path : PathBuf = if let Some(lib) = matches.value_of("PATH") { PathBuf::from(lib) } else { PathBuf::from(".") };
+  if let Some(members) = workspace_members(&library_path) { return for_each_member(&members,|member| check_library(matches,member)); }
+  check_library(matches,&library_path)
+}
+
+fn check_library(matches : &clap::ArgMatches, library_path : &PathBuf) -> Result<(),&'static str> {
+  let settings : Settings = select_profile(matches,library_path)?;
+  let destination_path : PathBuf = destination(library_path,&settings.output());
+
+  super::check(library_path,&destination_path,&feature_request(matches),&settings)?;
+  println!("No problems found in {}",Blue.paint(library_path.display().to_string()));
+  Ok(())
+}
+
+fn process_release(matches : &clap::ArgMatches) -> Result<(),&'static str> {
+  let library_path : PathBuf = if let Some(lib) = matches.value_of("PATH") { PathBuf::from(lib) } else { PathBuf::from(".") };
+  let mut destination_path = library_path.clone();
+  destination_path.push(lpsettings::get_value_or("lmake.compile-path","bin"));
+
+  if !processing::compile::validate_lualib_path(&library_path) { return Err("Not a valid library"); }
+
+  let version = local::release::release(&library_path,&destination_path,matches.value_of("VERSION").unwrap())?;
+  println!("Released {}",Blue.paint(version));
+  Ok(())
+}
+
+fn print_listings(listings : &Vec<&local::library::LibraryListing>, format : Option<&str>) {
+  match format {
+    Some("json") => {
+      match serde_json::to_string_pretty(listings) {
+        Err(error) => { output_error!("Error creating json: {}",Red.paint(error.to_string())); }
+        Ok(json) => { println!("{}",json); }
+      }
+    }
+    _ => { for listing in listings { println!("{}",listing); } }
+  }
 }
 
 pub fn app() -> clap::App<'static,'static> {
@@ -171,8 +419,182 @@ pub fn app() -> clap::App<'static,'static> {
         .help("Path to definition file")
         .value_name("PATH")
         .index(1))
+
+    // parameters
+      .arg(clap::Arg::with_name("jobs")
+        .help("Number of libraries to compile at the same time")
+        .long("jobs")
+        .short("j")
+        .takes_value(true)
+        .validator(validate_job_count))
+
+      .arg(clap::Arg::with_name("profile")
+        .help("The build profile to use for every library, dev or release or one from their definitions")
+        .long("profile")
+        .short("p")
+        .value_name("PROFILE")
+        .takes_value(true))
+      )
+
+
+  // TREE subapp
+    .subcommand(clap::SubCommand::with_name("tree")
+      .about("Displays the dependency tree of the library.")
+
+    // arguements
+      .arg(clap::Arg::with_name("PATH")
+        .help("Path to library")
+        .value_name("PATH")
+        .index(1))
+
+    // parameters
+      .arg(clap::Arg::with_name("format")
+        .help("Output format")
+        .long("format")
+        .short("f")
+        .possible_values(&["text","json","dot"])
+        .takes_value(true))
+
+      .arg(clap::Arg::with_name("features")
+        .help("Features to turn on, split by commas")
+        .long("features")
+        .value_name("FEATURES")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1))
+
+      .arg(clap::Arg::with_name("no-default-features")
+        .help("Doesn't turn on the library's default features")
+        .long("no-default-features"))
+
+      .arg(clap::Arg::with_name("profile")
+        .help("The build profile to use, dev or release or one from the library definition")
+        .long("profile")
+        .short("p")
+        .value_name("PROFILE")
+        .takes_value(true))
+      )
+
+  // LIST subapp
+    .subcommand(clap::SubCommand::with_name("list")
+      .about("Lists all the libraries in the local library folder.")
+
+    // parameters
+      .arg(clap::Arg::with_name("format")
+        .help("Output format")
+        .long("format")
+        .short("f")
+        .possible_values(&["text","json"])
+        .takes_value(true))
+      )
+
+  // SEARCH subapp
+    .subcommand(clap::SubCommand::with_name("search")
+      .about("Searches the local library folder by name, user and description.")
+
+    // arguements
+      .arg(clap::Arg::with_name("PATTERN")
+        .help("Text or regex to search for")
+        .value_name("PATTERN")
+        .required(true))
+
+    // parameters
+      .arg(clap::Arg::with_name("format")
+        .help("Output format")
+        .long("format")
+        .short("f")
+        .possible_values(&["text","json"])
+        .takes_value(true))
+      )
+
+  // INFO subapp
+    .subcommand(clap::SubCommand::with_name("info")
+      .about("Shows everything about a library.")
+
+    // arguements
+      .arg(clap::Arg::with_name("LIBRARY")
+        .help("Name of a library in the local library folder, or a path to a library")
+        .value_name("LIBRARY")
+        .required(true))
+      )
+
+  // TEST subapp
+    .subcommand(clap::SubCommand::with_name("test")
+      .about("Compiles the library and runs its test command.")
+
+    // arguements
+      .arg(clap::Arg::with_name("PATH")
+        .help("Path to library")
+        .value_name("PATH")
+        .index(1))
       )
 
+  // CHECK subapp
+    .subcommand(clap::SubCommand::with_name("check")
+      .about("Checks the library for problems without compiling it.")
+
+    // arguements
+      .arg(clap::Arg::with_name("PATH")
+        .help("Path to library")
+        .value_name("PATH")
+        .index(1))
+
+    // switches
+      .arg(clap::Arg::with_name("allow-unresolved")
+        .help("Only warns about @references that can't be found instead of failing")
+        .long("allow-unresolved"))
+
+      .arg(clap::Arg::with_name("deny-globals")
+        .help("Fails if the library sets or reads unknown globals")
+        .long("deny-globals"))
+
+    // parameters
+      .arg(clap::Arg::with_name("define")
+        .help("Sets a define used in the source files, KEY=VALUE")
+        .long("define")
+        .short("D")
+        .value_name("KEY=VALUE")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1))
+
+      .arg(clap::Arg::with_name("features")
+        .help("Features to turn on, split by commas")
+        .long("features")
+        .value_name("FEATURES")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1))
+
+      .arg(clap::Arg::with_name("no-default-features")
+        .help("Doesn't turn on the library's default features")
+        .long("no-default-features"))
+
+      .arg(clap::Arg::with_name("profile")
+        .help("The build profile to use, dev or release or one from the library definition")
+        .long("profile")
+        .short("p")
+        .value_name("PROFILE")
+        .takes_value(true))
+      )
+
+  // RELEASE subapp
+    .subcommand(clap::SubCommand::with_name("release")
+      .about("Bumps the library version, commits and tags it.")
+      .alias("publish")
+
+    // arguements
+      .arg(clap::Arg::with_name("VERSION")
+        .help("major, minor, patch or the new version")
+        .value_name("VERSION")
+        .required(true)
+        .index(1))
+
+      .arg(clap::Arg::with_name("PATH")
+        .help("Path to library")
+        .value_name("PATH")
+        .index(2))
+      )
 
   // COMPILE subapp
     .subcommand(clap::SubCommand::with_name("compile")
@@ -193,12 +615,70 @@ pub fn app() -> clap::App<'static,'static> {
         .help("Removes all comments from files")
         .long("remove-comments"))
 
+      .arg(clap::Arg::with_name("minify")
+        .help("Removes comments and extra whitespace")
+        .long("minify"))
+
+      .arg(clap::Arg::with_name("debug-build")
+        .help("Loads each file with its own chunk name so errors show the original file and line")
+        .long("debug-build"))
+
+      .arg(clap::Arg::with_name("allow-unresolved")
+        .help("Only warns about @references that can't be found instead of failing")
+        .long("allow-unresolved"))
+
+      .arg(clap::Arg::with_name("deny-globals")
+        .help("Fails if the library sets or reads unknown globals")
+        .long("deny-globals"))
+
     // parameters
       .arg(clap::Arg::with_name("compiled-name")
         .help("Set what to name the compiled file")
         .long("compiled-name")
         .short("c")
         .takes_value(true))
+
+      .arg(clap::Arg::with_name("jobs")
+        .help("Number of dependencies to compile at the same time")
+        .long("jobs")
+        .short("j")
+        .takes_value(true)
+        .validator(validate_job_count))
+
+      .arg(clap::Arg::with_name("define")
+        .help("Sets a define used in the source files, KEY=VALUE")
+        .long("define")
+        .short("D")
+        .value_name("KEY=VALUE")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1))
+
+      .arg(clap::Arg::with_name("features")
+        .help("Features to turn on, split by commas")
+        .long("features")
+        .value_name("FEATURES")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1))
+
+      .arg(clap::Arg::with_name("no-default-features")
+        .help("Doesn't turn on the library's default features")
+        .long("no-default-features"))
+
+      .arg(clap::Arg::with_name("profile")
+        .help("The build profile to use, dev or release or one from the library definition")
+        .long("profile")
+        .short("p")
+        .value_name("PROFILE")
+        .takes_value(true))
+
+      .arg(clap::Arg::with_name("target")
+        .help("Only builds this target from the library definition")
+        .long("target")
+        .short("t")
+        .value_name("TARGET")
+        .takes_value(true))
       
     )
 
@@ -6,6 +6,7 @@ extern crate git2;
 extern crate ansi_term; use ansi_term::Colour::{Red,Yellow,Blue,Green};
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 
 #[macro_use]
 extern crate output;
@@ -13,24 +14,45 @@ extern crate love;
 extern crate lpsettings;
 extern crate version; use version::version::Version;
 extern crate base64;
+extern crate threadpool;
+extern crate num_cpus;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path,PathBuf};
 use std::fs;
 use std::io::Write;
+use std::process::Command;
 
+use library::lualibdef::{LibraryDefinition,FeatureRequest,Target};
+use processing::compile::ResolvedDependency;
+use processing::settings::Settings;
+
+#[macro_use]
+mod messages;
 pub mod interface;
 mod processing;
 mod library;
 mod local;
+mod lint;
 
 pub static LIBDEFFILE : &str = "lib.toml";
+pub static WORKSPACEFILE : &str = "lmake-workspace.toml";
 
-pub fn compile(path : &PathBuf, dest : &PathBuf, dep : bool, version : &Option<Version>) -> Result<PathBuf,&'static str> {
+pub fn compile(path : &PathBuf, dest : &PathBuf, dep : bool, version : &Option<Version>, features : &FeatureRequest, target : &Option<String>, settings : &Settings) -> Result<Vec<PathBuf>,&'static str> {
+  //! compiles the library, every one of its targets if it has them (or only `target`), and returns the
+  //! path of every file that was written.
+  compile_with_parents(path,dest,dep,version,features,target,settings,&[]).map(|(compiled_paths,_)| compiled_paths)
+}
 
-  let mut final_compiled_path : Option<PathBuf> = None;
+#[allow(clippy::too_many_arguments)]
+fn compile_with_parents(path : &PathBuf, dest : &PathBuf, dep : bool, version : &Option<Version>, features : &FeatureRequest, target : &Option<String>, settings : &Settings, parents : &[String]) -> Result<(Vec<PathBuf>,Vec<ResolvedDependency>),&'static str> {
+  //! `compile`, with the names of the libraries that are being compiled and depend on this one. Also
+  //! returns the dependencies that were compiled into it.
 
-  match processing::compile::validate_lualib_path(&path) {
+  let mut compiled_paths : Vec<PathBuf> = Vec::new();
+  let mut resolved_dependencies : Vec<ResolvedDependency> = Vec::new();
+
+  match processing::compile::validate_lualib_path(path) {
     false => { 
       output_error!("Path {} is not a valid lua lib: {}", Red.paint(path.display().to_string()),Yellow.paint(format!("{} not found",LIBDEFFILE)));
       return Err("Not a valid library");
@@ -38,9 +60,13 @@ pub fn compile(path : &PathBuf, dest : &PathBuf, dep : bool, version : &Option<V
     true => { output_debug!("Valid lua library"); }
   }
 
-  match library::luafile::get_lualib_settings(&path) {
+  match library::luafile::get_lualib_settings(path) {
     None => { output_error!("Error loading lualib definition file for {}",&path.display().to_string()); }
-    Some(definition) => {
+    Some(mut definition) => {
+      if let Err(error) = definition.with_features(features) {
+        output_error!("{}",Red.paint(error));
+        return Err("Unknown feature.");
+      }
 
       // first will check if the library is compatible with the version of love being used.
       // Right now it only makes a warning
@@ -52,62 +78,214 @@ pub fn compile(path : &PathBuf, dest : &PathBuf, dep : bool, version : &Option<V
         }
       }
 
-      // builds the output path, can either do
-      // (1) the library name
-      // (2) the library name and version with the --name-with-version switch
-      // (3) an cli provided name with the --compiled-name switch with a value being the new name. can be with or without the .lua extension
-      // also puts the dependencies into a cache dep folder.
-      let mut compiled_file_path = if dep { 
-        let mut new_folder = if let Ok(mut folder) = lpsettings::get_settings_folder() { folder } else { PathBuf::from(".") };
-        new_folder.push(lpsettings::get_value_or("core.cache","cache"));
-        new_folder
-      } else { dest.clone() };
-      std::fs::create_dir_all(&compiled_file_path);
-      compiled_file_path.push(processing::gen::compiled_file_name(&definition,dep));
-
-      // looks at the requires and processess them.
+      let targets = match definition.select_targets(target,dep) {
+        Ok(targets) => { targets }
+        Err(error) => { output_error!("{}",Red.paint(error)); return Err("Unknown target."); }
+      };
+
+      let settings : Settings = if dep { settings.for_dependency(&definition) } else { settings.clone() };
+
+      // the dependencies are compiled once and shared by all the targets.
       let mut preload_hash : HashMap<String,String> = HashMap::new();
-      let mut array_of_preloads : Vec<String> = Vec::new();
-
-      // processing the components of the definition file
-      processing::compile::requires(&path,&definition,&mut array_of_preloads,&mut preload_hash);
-      processing::compile::dependencies(&dest,&definition,&mut array_of_preloads,&mut preload_hash);
-
-      // the buffer for the compiled library contents
-      let mut file_buffer : String = String::new();
-
-      // adding the dependencies and requires source code
-      processing::buffer::inject_comment_header(&mut file_buffer);
-      processing::buffer::inject_preloads(&mut file_buffer,&array_of_preloads);  // writes the preloaded stuff          
-      processing::buffer::inject_basefill(&mut file_buffer,&definition.to_compiled_base_file(&preload_hash));  // writes the basefill stuff
-
-      // processess the resulting buffer, formatting, var names, etc..
-      processing::buffer::remove_comments(&mut file_buffer);
-      processing::buffer::remove_blank_lines(&mut file_buffer);
-      processing::buffer::process_depends_references(&mut file_buffer,&preload_hash); // takes all the @ references and replaces them if dependencies.
-      processing::buffer::process_internal_references(&mut file_buffer,&definition.requires,&preload_hash); // takes all the @ references and replaces them if internal references.
-
-      // does optional stuff, like asset replacement
-      processing::buffer::embed_assets(&mut file_buffer,&path,&definition.options);
-
-      // creates the compiled output file.
-      match fs::File::create(&compiled_file_path) {
-        Err(error) => { output_error!("Could not create \'{}\': {}",Red.paint(compiled_file_path.display().to_string()),Yellow.paint(error.to_string())); },
-        Ok(mut file) => { 
-          // writes the buffer to the file
-          let result_text = if let Err(error) = file.write_all(&file_buffer.as_bytes()) { format!("{}: {}",Red.paint("Failed"), Yellow.paint(error.to_string())) }
-            else { 
-              final_compiled_path = Some(compiled_file_path.clone());
-              format!("{}",Green.paint("Successful"))
-          };
-          output_debug!("Compiling {}/{} ({}): {}",Blue.paint(definition.user.clone()),Blue.paint(definition.name.clone()),Yellow.paint(definition.version.to_string().clone()),result_text);
-        } 
+      let mut dependency_preloads : Vec<String> = Vec::new();
+      resolved_dependencies = processing::compile::dependencies(dest,&definition,&settings,parents,&mut dependency_preloads,&mut preload_hash);
+
+      // only for the library being compiled, there isn't much someone can do about the dependencies.
+      if !dep { 
+        lint::unused::warn_unused(path,dest,&definition);
+        lint::love::check_library(path,&definition);
+        if !lint::dialect::check_library(path,&definition) { return Err("Invalid target-lua."); }
+        if !lint::globals::check_library(path,&definition,settings.deny_globals) { return Err("Globals used in the library."); }
       }
-      
 
+      let requires = definition.requires.clone();
+      let options = definition.options.clone();
+
+      for target in targets {
+        let compiled_path = build(path,dest,dep,&mut definition,&target,&settings,&dependency_preloads,&preload_hash,&resolved_dependencies);
+
+        // back to the full library for the next target
+        definition.requires = requires.clone();
+        definition.options = options.clone();
+
+        compiled_paths.push(compiled_path?);
+      }
     }
   }
 
-  if let Some(path) = final_compiled_path { return Ok(path); }
+  if !compiled_paths.is_empty() { return Ok((compiled_paths,resolved_dependencies)); }
   Err("General compilation error.")
-}
\ No newline at end of file
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build(path : &PathBuf, dest : &Path, dep : bool, definition : &mut LibraryDefinition, target : &Option<Target>, settings : &Settings, dependency_preloads : &[String], dependency_hash : &HashMap<String,String>, resolved_dependencies : &[ResolvedDependency]) -> Result<PathBuf,&'static str> {
+  //! builds a single compiled file, for one target or for the whole library.
+
+  // the target's profile goes on top of the one the library is built with, a dependency is built
+  // the same way as the library that needs it.
+  let mut settings : Settings = settings.clone();
+  let mut output : Option<String> = None;
+  if let Some(ref target) = *target {
+    output_debug!("Building the target {}",Blue.paint(target.name.clone()));
+    if let Err(error) = definition.apply_target(target) { output_error!("{}",Red.paint(error)); return Err("Invalid target."); }
+    if let (false,Some(profile_name)) = (dep,&target.profile) {
+      match definition.get_profile(profile_name) {
+        Err(error) => { output_error!("{}",Red.paint(error)); return Err("Unknown profile."); }
+        Ok(profile) => { output = profile.output.clone(); settings = settings.with_profile(profile_name,profile); }
+      }
+    }
+  }
+
+  // builds the output path, can either do
+  // (1) the library name
+  // (2) the library name and version with the --name-with-version switch
+  // (3) an cli provided name with the --compiled-name switch with a value being the new name. can be with or without the .lua extension
+  // (4) the target's name, or its compiled-name
+  // also puts the dependencies into a cache dep folder.
+  let mut compiled_file_path = if dep { 
+    let mut new_folder = if let Ok(folder) = lpsettings::get_settings_folder() { folder } else { PathBuf::from(".") };
+    new_folder.push(lpsettings::get_value_or("core.cache","cache"));
+    new_folder
+  } else if let Some(output) = output {
+    let mut new_folder = path.clone();
+    new_folder.push(output);
+    new_folder
+  } else { dest.to_path_buf() };
+  if let Err(error) = std::fs::create_dir_all(&compiled_file_path) {
+    output_error!("Could not create the folder \'{}\': {}",Red.paint(compiled_file_path.display().to_string()),Yellow.paint(error.to_string()));
+    return Err("Could not create the output folder.");
+  }
+  compiled_file_path.push(processing::gen::compiled_file_name(definition,dep,target,&settings));
+
+  // looks at the requires and processess them.
+  let mut preload_hash : HashMap<String,String> = dependency_hash.clone();
+  let mut file_preloads : Vec<String> = Vec::new();
+  let mut referenced : Vec<String> = Vec::new();
+  if !processing::compile::requires(path,definition,&settings,&mut file_preloads,&mut preload_hash,&mut referenced) { return Err("Unresolved references."); }
+
+  // only the dependencies this build uses, a target with fewer requires might not need all of them.
+  // the preloads are in the same order as the resolved dependencies.
+  let mut array_of_preloads : Vec<String> = Vec::new();
+  let mut used_dependencies : Vec<ResolvedDependency> = Vec::new();
+  for (dependency,preload) in resolved_dependencies.iter().zip(dependency_preloads.iter()) {
+    if target.is_none() || referenced.contains(&dependency.name) {
+      array_of_preloads.push(preload.clone());
+      used_dependencies.push(dependency.clone());
+    }
+  }
+  array_of_preloads.extend(file_preloads);
+
+  // the buffer for the compiled library contents
+  let mut file_buffer : String = String::new();
+
+  // adding the dependencies and requires source code
+  processing::buffer::inject_preloads(&mut file_buffer,&array_of_preloads);  // writes the preloaded stuff          
+  processing::buffer::inject_basefill(&mut file_buffer,&definition.to_compiled_base_file(&preload_hash));  // writes the basefill stuff
+
+  // processess the resulting buffer, formatting, var names, etc..
+  if settings.remove_comments() { processing::buffer::remove_comments(&mut file_buffer); }
+  if settings.minify() { processing::buffer::minify(&mut file_buffer,settings.debug_build()); }
+  // debug builds need to keep every line so the line numbers match the source files.
+  if !settings.debug_build() { processing::buffer::remove_blank_lines(&mut file_buffer); }
+
+  // the licenses of everything that was embedded, only for the library being compiled since it has all of them.
+  if !dep { processing::buffer::inject_license_notices(&mut file_buffer,&processing::notices::to_header(&processing::notices::dependencies(&used_dependencies))); }
+  processing::buffer::inject_comment_header(&mut file_buffer,path,definition);
+
+  // does optional stuff, like asset replacement
+  processing::buffer::embed_assets(&mut file_buffer,path,&definition.options);
+  if processing::build::is_enabled(definition) { processing::build::inject_build_info(&mut file_buffer,path,&used_dependencies); }
+
+  // creates the compiled output file, a dependency could be read or written by another thread at the same time.
+  let lock = processing::compile::cache_lock(&compiled_file_path);
+  let _guard = lock.lock().unwrap_or_else(|error| error.into_inner());
+  match fs::File::create(&compiled_file_path) {
+    Err(error) => { 
+      output_error!("Could not create \'{}\': {}",Red.paint(compiled_file_path.display().to_string()),Yellow.paint(error.to_string()));
+      Err("Could not create the compiled file.")
+    },
+    Ok(mut file) => { 
+      // writes the buffer to the file
+      match file.write_all(file_buffer.as_bytes()) {
+        Err(error) => {
+          output_debug!("Compiling {}/{} ({}): {}: {}",Blue.paint(definition.user.clone()),Blue.paint(definition.name.clone()),Yellow.paint(definition.version.to_string().clone()),Red.paint("Failed"),Yellow.paint(error.to_string()));
+          Err("Could not write the compiled file.")
+        }
+        Ok(_) => {
+          output_debug!("Compiling {}/{} ({}): {}",Blue.paint(definition.user.clone()),Blue.paint(definition.name.clone()),Yellow.paint(definition.version.to_string().clone()),Green.paint("Successful"));
+          Ok(compiled_file_path)
+        }
+      }
+    } 
+  }
+}
+
+pub fn check(path : &PathBuf, dest : &PathBuf, features : &FeatureRequest, settings : &Settings) -> Result<(),&'static str> {
+  //! checks the library for problems without compiling it or any of its dependencies.
+
+  if !processing::compile::validate_lualib_path(path) { return Err("Not a valid library"); }
+
+  match library::luafile::get_lualib_settings(path) {
+    None => { Err("Error loading library definition.") }
+    Some(mut definition) => {
+      if let Err(error) = definition.with_features(features) {
+        output_error!("{}",Red.paint(error));
+        return Err("Unknown feature.");
+      }
+
+      // the dependencies aren't compiled, so they get a stand in preload name to resolve the references.
+      let mut preload_hash : HashMap<String,String> = HashMap::new();
+      if let Some(ref dependencies) = definition.dependencies {
+        for (name,_) in dependencies.iter() { preload_hash.insert(name.clone(),name.clone()); }
+      }
+
+      let success : bool = processing::compile::requires(path,&definition,settings,&mut Vec::new(),&mut preload_hash,&mut Vec::new());
+      lint::unused::warn_unused(path,dest,&definition);
+      lint::love::check_library(path,&definition);
+      let dialect : bool = lint::dialect::check_library(path,&definition);
+      let globals : bool = lint::globals::check_library(path,&definition,settings.deny_globals);
+
+      if success && dialect && globals { Ok(()) } else { Err("Check failed.") }
+    }
+  }
+}
+
+pub fn test(path : &PathBuf, dest : &PathBuf, settings : &Settings) -> Result<(),&'static str> {
+  //! compiles the library and then runs the `test` command from its definition file inside the library folder,
+  //! once for every file that was compiled.
+  //!
+  //! the path of the compiled library is given to the command as `LMAKE_COMPILED_PATH`.
+
+  let compiled_paths : Vec<PathBuf> = compile(path,dest,false,&None,&FeatureRequest::default(),&None,settings)?;
+
+  match library::luafile::get_lualib_settings(path) {
+    None => { Err("Error loading library definition.") }
+    Some(definition) => {
+      match definition.test {
+        None => { 
+          output_println!("No {} command defined in {}, nothing to run.",Yellow.paint("test"),LIBDEFFILE);
+          Ok(())
+        }
+        Some(ref command) => {
+          for compiled_path in compiled_paths.iter() {
+            output_debug!("Running tests with \'{}\' on {}",Blue.paint(command.to_string()),Yellow.paint(compiled_path.display().to_string()));
+
+            let mut process = if cfg!(windows) { let mut process = Command::new("cmd"); process.arg("/C"); process } 
+              else { let mut process = Command::new("sh"); process.arg("-c"); process };
+
+            match process.arg(command).current_dir(path).env("LMAKE_COMPILED_PATH",compiled_path).status() {
+              Err(error) => { 
+                output_error!("Could not run \'{}\': {}",Red.paint(command.to_string()),Yellow.paint(error.to_string()));
+                return Err("Could not run tests.");
+              }
+              Ok(status) => {
+                if !status.success() { return Err("Tests failed."); }
+              }
+            }
+          }
+          Ok(())
+        }
+      }
+    }
+  }
+}
@@ -1,33 +1,64 @@
 use ansi_term::Colour::{Yellow,Red};
 use toml;
 
-use std::path::PathBuf;
+use std::path::{Path,PathBuf};
 use std::fs;
 use std::io::prelude::*;
 
 use LIBDEFFILE;
 use library::lualibdef::LibraryDefinition;
+use library::workspace;
 
-pub fn get_lualib_settings(library_root_path : &PathBuf) -> Option<LibraryDefinition> {
+pub fn get_lualib_settings(library_root_path : &Path) -> Option<LibraryDefinition> {
   //! loads the lib.toml file into a LibraryDefinition and returns that
 
-  let mut path_lib_def_file = library_root_path.clone();
+  let mut path_lib_def_file = library_root_path.to_path_buf();
   path_lib_def_file.push(LIBDEFFILE);
 
   let raw_lib_def_contents = get_raw_file_contents(&path_lib_def_file);
-  let definition : Result<LibraryDefinition,toml::de::Error> = toml::from_str(&raw_lib_def_contents);
+  match parse_lualib_settings(&raw_lib_def_contents) {
+    None => { None }
+    Some(mut definition) => {
+      definition.with_workspace(library_root_path,workspace::containing(library_root_path));
+      Some(definition)
+    }
+  }
+}
+
+pub fn parse_lualib_settings(raw_lib_def_contents : &str) -> Option<LibraryDefinition> {
+  //! parses the contents of a lib.toml file into a LibraryDefinition
+
+  let definition : Result<LibraryDefinition,toml::de::Error> = toml::from_str(raw_lib_def_contents);
 
   match definition {
-    Err(error) => { output_debug!("Error parsing the library definition file \'{}\': {}",Yellow.paint(LIBDEFFILE),Red.paint(error.to_string())); return None; }
+    Err(error) => { output_debug!("Error parsing the library definition file \'{}\': {}",Yellow.paint(LIBDEFFILE),Red.paint(error.to_string())); None}
     Ok(def) => {
+      if let Err(error) = def.validate() {
+        output_error!("Error in the library definition file \'{}\': {}",Yellow.paint(LIBDEFFILE),Red.paint(error));
+        return None;
+      }
       output_debug!("Loaded the library {}",def.to_string());
-      return Some(def);
+      Some(def)
     }
   }
 }
 
-pub fn create_preload_string(path : &PathBuf,prename : &str) -> String {
-  let contents = get_raw_file_contents(&path);
+pub fn create_preload_string(contents : &str,prename : &str,chunkname : &str,debug_build : bool) -> String {
+  //! wraps the contents of a file in a preload function.
+  //!
+  //! for debug builds the file is loaded from a string with `chunkname` as its name, so errors and tracebacks
+  //! point to the original file and line instead of the compiled library.
+
+  if debug_build {
+    // finds a long bracket level that isn't used inside the file
+    let mut level : String = String::new();
+    while contents.contains(&format!("]{}]",level)) { level.push('='); }
+
+    return format!("\npackage.preload['{}'] = assert((loadstring or load)([{}[\n\
+      {}\n\
+      ]{}], '@{}'))\n\
+      ",prename,level,contents,level,chunkname);
+  }
 
   format!("\npackage.preload['{}'] = (function(...)\n\
     {}\n\
@@ -35,10 +66,10 @@ pub fn create_preload_string(path : &PathBuf,prename : &str) -> String {
     ",prename,contents)
 }
 
-fn get_raw_file_contents(path : &PathBuf) -> String {
+pub fn get_raw_file_contents(path : &PathBuf) -> String {
 
   let mut file_contents = String::new();
-  let file = fs::File::open(&path);
+  let file = fs::File::open(path);
   match file { 
     Err(error) => { output_error!("Could not open \'{}\': {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string())); }
     Ok(mut file) => {
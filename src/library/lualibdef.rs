@@ -1,6 +1,10 @@
 use std::collections::HashMap;
+use std::path::{Path,PathBuf};
+use toml;
 use version::version::Version;
 use library::multivalue::Multivalue;
+use library::spdx;
+use library::workspace::Workspace;
 
 #[derive(Deserialize)]
 pub struct LibraryDefinition {
@@ -8,21 +12,327 @@ pub struct LibraryDefinition {
   pub user : String,
   pub author : String,
   pub email : Option<String>,
+  pub description : Option<String>,
+  pub license : Option<String>,
+  pub homepage : Option<String>,
+  pub keywords : Option<Vec<String>>,
+  pub authors : Option<Vec<String>>,
+  pub header : Option<String>,
   pub version : Version,
   pub love : Option<Version>,
+  #[serde(rename = "target-lua")]
+  pub target_lua : Option<String>,
 
   pub upstream : Option<String>,
+  pub test : Option<String>,
+  pub ignore : Option<Vec<String>>,
+  pub globals : Option<Vec<String>>,
   pub requires : Option<HashMap<String,String>>,
-  pub dependencies : Option<HashMap<String,HashMap<String,String>>>,
-  pub options : Option<HashMap<String,Multivalue>>
+  pub internal : Option<HashMap<String,String>>,
+  pub dependencies : Option<HashMap<String,Dependency>>,
+  pub options : Option<HashMap<String,Multivalue>>,
+  pub defines : Option<HashMap<String,toml::Value>>,
+  pub profile : Option<HashMap<String,Profile>>,
+  pub features : Option<HashMap<String,Feature>>,
+  pub target : Option<Vec<Target>>,
+  #[serde(rename = "default-features")]
+  pub default_features : Option<Vec<String>>,
 
+  // the features that were turned on with `with_features`
+  #[serde(skip_deserializing)]
+  pub enabled_features : Vec<String>,
+
+  // where the library is, and the workspace it is a member of, set with `with_workspace`
+  #[serde(skip_deserializing)]
+  pub root : Option<PathBuf>,
+  #[serde(skip_deserializing)]
+  pub workspace : Option<Workspace>,
+
+}
+
+#[derive(Deserialize,Clone)]
+pub struct Target {
+  pub name : String,
+  #[serde(rename = "compiled-name")]
+  pub compiled_name : Option<String>,
+  pub requires : Option<Vec<String>>,
+  pub options : Option<HashMap<String,Multivalue>>,
+  pub profile : Option<String>,
+}
+
+#[derive(Deserialize,Clone)]
+pub struct Feature {
+  pub requires : Option<HashMap<String,String>>,
+  pub internal : Option<HashMap<String,String>>,
+  pub dependencies : Option<HashMap<String,Dependency>>,
+  pub features : Option<Vec<String>>,
+}
+
+#[derive(Deserialize,Clone)]
+pub struct Dependency {
+  // the library's name, when it isn't the same as the key
+  pub name : Option<String>,
+  pub version : Option<String>,
+  pub path : Option<String>,
+  pub target : Option<String>,
+  #[serde(default)]
+  pub features : Vec<String>,
+  #[serde(rename = "default-features", default = "default_features")]
+  pub default_features : bool,
+}
+
+fn default_features() -> bool { true }
+
+impl Dependency {
+  pub fn library_name(&self, key : &str) -> String {
+    //! the name of the library, `key` is the name it has in `[dependencies]`.
+    self.name.clone().unwrap_or(key.to_string())
+  }
+
+  pub fn feature_request(&self) -> FeatureRequest {
+    FeatureRequest { features : self.features.clone(), no_default_features : !self.default_features }
+  }
+}
+
+#[derive(Clone,Default,Debug)]
+pub struct FeatureRequest {
+  pub features : Vec<String>,
+  pub no_default_features : bool,
+}
+
+impl FeatureRequest {
+  pub fn new(features : &str, no_default_features : bool) -> FeatureRequest {
+    //! `features` is a list split by commas or spaces.
+    FeatureRequest {
+      features : features.split(|c : char| c == ',' || c.is_whitespace()).filter(|feature| !feature.is_empty()).map(|feature| feature.to_string()).collect(),
+      no_default_features,
+    }
+  }
+}
+
+#[derive(Deserialize,Clone,Default)]
+pub struct Profile {
+  pub inherits : Option<String>,
+  #[serde(rename = "remove-comments")]
+  pub remove_comments : Option<bool>,
+  pub minify : Option<bool>,
+  #[serde(rename = "debug-build")]
+  pub debug_build : Option<bool>,
+  #[serde(rename = "name-with-version")]
+  pub name_with_version : Option<bool>,
+  #[serde(rename = "compiled-name")]
+  pub compiled_name : Option<String>,
+  pub output : Option<String>,
+  pub defines : Option<HashMap<String,toml::Value>>,
+}
+
+impl Profile {
+  pub fn default_for(name : &str) -> Option<Profile> {
+    //! the built in profiles, `dev` is the same as not using a profile, `release` makes the smallest file.
+    match name {
+      "dev" => { Some(Profile::default()) }
+      "release" => { Some(Profile { remove_comments : Some(true), minify : Some(true), .. Profile::default() }) }
+      _ => { None }
+    }
+  }
+
+  pub fn inherit(self, base : Profile) -> Profile {
+    //! fills everything that isn't set with the value from `base`.
+    let defines : Option<HashMap<String,toml::Value>> = match (base.defines,self.defines) {
+      (None,None) => { None }
+      (base_defines,defines) => {
+        let mut merged : HashMap<String,toml::Value> = base_defines.unwrap_or(HashMap::new());
+        if let Some(defines) = defines { merged.extend(defines); }
+        Some(merged)
+      }
+    };
+
+    Profile {
+      inherits : self.inherits,
+      remove_comments : self.remove_comments.or(base.remove_comments),
+      minify : self.minify.or(base.minify),
+      debug_build : self.debug_build.or(base.debug_build),
+      name_with_version : self.name_with_version.or(base.name_with_version),
+      compiled_name : self.compiled_name.or(base.compiled_name),
+      output : self.output.or(base.output),
+      defines,
+    }
+  }
 }
 
 impl LibraryDefinition {
+  #[allow(clippy::inherent_to_string)]
   pub fn to_string(&self) -> String {
     format!("{}/{} ({})",&self.user,&self.name,&self.version.to_string())
   }
 
+  pub fn validate(&self) -> Result<(),String> {
+    //! checks the fields that have a format, called when the definition is loaded.
+    if let Some(ref license) = self.license {
+      match spdx::validate(license) {
+        Err(error) => { return Err(format!("license `{}`: {}",license,error)); }
+        Ok(unknown) => { for id in unknown { output_warning!("{} isn't a known SPDX license id, in the license of {}",id,&self.name); } }
+      }
+    }
+
+    if let Some(ref homepage) = self.homepage {
+      if !homepage.starts_with("http://") && !homepage.starts_with("https://") { return Err(format!("homepage `{}` has to be a http or https url",homepage)); }
+    }
+
+    if let Some(ref keywords) = self.keywords {
+      for keyword in keywords.iter() {
+        if keyword.is_empty() || keyword.chars().any(|c| c.is_whitespace()) { return Err(format!("keyword `{}` can't be empty or have spaces",keyword)); }
+      }
+    }
+
+    if let Some(ref authors) = self.authors {
+      if authors.iter().any(|author| author.trim().is_empty()) { return Err("authors can't be empty".to_string()); }
+    }
+
+    Ok(())
+  }
+
+  pub fn with_features(&mut self, request : &FeatureRequest) -> Result<(),String> {
+    //! turns on the requested features (and the default ones), adding their requires, internal modules
+    //! and dependencies to the definition's own.
+    let mut wanted : Vec<String> = request.features.clone();
+    if !request.no_default_features { if let Some(ref defaults) = self.default_features { wanted.extend(defaults.iter().cloned()); } }
+
+    let features : HashMap<String,Feature> = self.features.clone().unwrap_or_default();
+    let mut enabled : Vec<String> = Vec::new();
+    while let Some(name) = wanted.pop() {
+      if enabled.contains(&name) { continue; }
+      match features.get(&name) {
+        None => { return Err(format!("{} doesn't have a feature called {}",&self.name,name)); }
+        Some(feature) => {
+          if let Some(ref others) = feature.features { wanted.extend(others.iter().cloned()); }
+          enabled.push(name);
+        }
+      }
+    }
+    enabled.sort();
+
+    for name in enabled.iter() {
+      let feature : &Feature = &features[name];
+      if let Some(ref requires) = feature.requires { self.requires.get_or_insert(HashMap::new()).extend(requires.clone()); }
+      if let Some(ref internal) = feature.internal { self.internal.get_or_insert(HashMap::new()).extend(internal.clone()); }
+      if let Some(ref dependencies) = feature.dependencies { self.dependencies.get_or_insert(HashMap::new()).extend(dependencies.clone()); }
+    }
+
+    self.enabled_features = enabled;
+    Ok(())
+  }
+
+  pub fn with_workspace(&mut self, path : &Path, workspace : Option<Workspace>) {
+    //! remembers where the library is and its workspace, the profiles of the workspace are used for the
+    //! ones the library doesn't have.
+    if let Some(profiles) = workspace.as_ref().and_then(|workspace| workspace.profile.as_ref()) {
+      let own = self.profile.get_or_insert(HashMap::new());
+      for (name,profile) in profiles.iter() {
+        if !own.contains_key(name) { own.insert(name.clone(),profile.clone()); }
+      }
+    }
+    self.root = Some(path.to_path_buf());
+    self.workspace = workspace;
+  }
+
+  pub fn dependency_source(&self, library_name : &str, dependency : &Dependency) -> Option<PathBuf> {
+    //! the folder a dependency is built from directly, either its `path` or a member of the same workspace
+    //! with that name. `None` means it is found by version like always.
+    if let Some(ref path) = dependency.path {
+      let mut source : PathBuf = self.root.clone().unwrap_or(PathBuf::from("."));
+      source.push(path);
+      return Some(source);
+    }
+    self.workspace.as_ref().and_then(|workspace| workspace.find_member(library_name))
+  }
+
+  pub fn select_targets(&self, name : &Option<String>, dep : bool) -> Result<Vec<Option<Target>>,String> {
+    //! the targets to build, `None` is the library as it is when it doesn't have any targets.
+    //!
+    //! a dependency is built whole unless it picked a target.
+    let targets : Vec<Target> = self.target.clone().unwrap_or_default();
+
+    if let Some(ref name) = *name {
+      return match targets.into_iter().find(|target| &target.name == name) {
+        Some(target) => { Ok(vec![Some(target)]) }
+        None => { Err(format!("{} doesn't have a target called {}",&self.name,name)) }
+      };
+    }
+
+    if targets.is_empty() || dep { return Ok(vec![None]); }
+    Ok(targets.into_iter().map(Some).collect())
+  }
+
+  pub fn apply_target(&mut self, target : &Target) -> Result<(),String> {
+    //! keeps only the target's requires and adds its options.
+    if let Some(ref keys) = target.requires {
+      let requires : HashMap<String,String> = self.requires.clone().unwrap_or_default();
+      let mut subset : HashMap<String,String> = HashMap::new();
+      for key in keys.iter() {
+        match requires.get(key) {
+          Some(file) => { subset.insert(key.clone(),file.clone()); }
+          None => { return Err(format!("the target {} uses the require {}, which isn't in [requires]",&target.name,key)); }
+        }
+      }
+      self.requires = Some(subset);
+    }
+
+    if let Some(ref options) = target.options {
+      self.options.get_or_insert(HashMap::new()).extend(options.clone());
+    }
+    Ok(())
+  }
+
+  pub fn feature_files(&self) -> Vec<String> {
+    //! the files of every feature, on or not.
+    let mut files : Vec<String> = Vec::new();
+    if let Some(ref features) = self.features {
+      for feature in features.values() {
+        if let Some(ref requires) = feature.requires { files.extend(requires.values().cloned()); }
+        if let Some(ref internal) = feature.internal { files.extend(internal.values().cloned()); }
+      }
+    }
+    files
+  }
+
+  pub fn get_profile(&self, name : &str) -> Result<Profile,String> {
+    //! the profile called `name` with everything it inherits filled in.
+    //!
+    //! a profile without `inherits` starts from the built in profile with the same name, if there is one.
+    let mut chain : Vec<String> = Vec::new();
+    self.resolve_profile(name,&mut chain)
+  }
+
+  fn resolve_profile(&self, name : &str, chain : &mut Vec<String>) -> Result<Profile,String> {
+    if chain.iter().any(|seen| seen == name) { return Err(format!("profile {} inherits itself: {} -> {}",name,chain.join(" -> "),name)); }
+    chain.push(name.to_string());
+
+    let profile : Option<Profile> = self.profile.as_ref().and_then(|profiles| profiles.get(name).cloned());
+    match profile {
+      None => { Profile::default_for(name).ok_or(format!("there isn't a profile called {}",name)) }
+      Some(profile) => {
+        let base : Profile = match profile.inherits {
+          Some(ref parent) => { self.resolve_profile(parent,chain)? }
+          None => { Profile::default_for(name).unwrap_or_default() }
+        };
+        Ok(profile.inherit(base))
+      }
+    }
+  }
+
+  pub fn references(&self) -> Option<HashMap<String,String>> {
+    //! all the names that can be used with `@references`, the requires and the internal modules.
+    //! 
+    //! internal modules are only preloaded, they aren't added to the library table.
+    if self.requires.is_none() && self.internal.is_none() { return None; }
+
+    let mut references : HashMap<String,String> = HashMap::new();
+    if let Some(ref internal) = self.internal { for (entry,file) in internal.iter() { references.insert(entry.clone(),file.clone()); } }
+    if let Some(ref requires) = self.requires { for (entry,file) in requires.iter() { references.insert(entry.clone(),file.clone()); } }
+    Some(references)
+  }
+
   pub fn to_compiled_base_file(&self,preload_hash : &HashMap<String,String>) -> String {
     //! creates the text block for the library in lua. the `local library .......... return library` part.
     
@@ -31,6 +341,19 @@ impl LibraryDefinition {
       &self.name,&self.user,&self.author,&self.version.to_string()
     );
 
+    if let Some(ref love) = self.love { info = format!("{}\nlibrary.love = '{}'",info,love); }
+    if let Some(ref description) = self.description { info = format!("{}\nlibrary.description = {}",info,lua_string(description)); }
+    if let Some(ref license) = self.license { info = format!("{}\nlibrary.license = {}",info,lua_string(license)); }
+    if let Some(ref homepage) = self.homepage { info = format!("{}\nlibrary.homepage = {}",info,lua_string(homepage)); }
+    if let Some(ref keywords) = self.keywords { info = format!("{}\nlibrary.keywords = {}",info,lua_list(keywords)); }
+    if let Some(ref authors) = self.authors { info = format!("{}\nlibrary.authors = {}",info,lua_list(authors)); }
+    if let Some(ref features) = self.features {
+      let mut names : Vec<&String> = features.keys().collect();
+      names.sort();
+      let states : Vec<String> = names.iter().map(|name| format!("[{}] = {}",lua_string(name),self.enabled_features.contains(name))).collect();
+      info = format!("{}\nlibrary.features = {{ {} }}",info,states.join(", "));
+    }
+
     // for "_" if its used
     let mut library_inital : Option<String> = None;
 
@@ -51,7 +374,7 @@ impl LibraryDefinition {
           } else {
             // splits the name to que up empty tables to make
             let mut split : Vec<&str> = entry.split(".").collect();
-            while split.len() > 0 {
+            while !split.is_empty() {
               let temp_word = split.join(".");
               if !empty_requires.contains(&temp_word) {
                 empty_requires.insert(0,temp_word);
@@ -80,11 +403,232 @@ impl LibraryDefinition {
       info = format!("{}\nlibrary.{} = require (\"{}\")",info,part.0,part.1);
     }
 
+    // the guard goes first, before any of the requires are loaded
+    let guard : String = if let Some(guard) = self.love_guard() { format!("{}\n",guard) } else { String::new() };
+
     if let Some(base) = library_inital {
-      format!("{}\n{}\nreturn library\n",base,info)
+      format!("{}{}\n{}\nreturn library\n",guard,base,info)
     } else {
-      format!("local library = {{}}\n{}\nreturn library\n", info)
+      format!("{}local library = {{}}\n{}\nreturn library\n",guard,info)
     }
   }
 
-}
\ No newline at end of file
+  fn love_guard(&self) -> Option<String> {
+    //! the check for the running LÖVE version, if the `love-guard` option is set.
+    //!
+    //! `love-guard = "error"` (or `true`) stops loading the library, `"warn"` only prints a warning.
+    //! nothing is checked outside of LÖVE or before 0.9.1, where there isn't a `love.getVersion`.
+    let love : &Version = match self.love { None => { return None; } Some(ref love) => { love } };
+
+    let mode : &str = match self.options.as_ref().and_then(|options| options.get("love-guard")) {
+      None | Some(&Multivalue::Switch(false)) => { return None; }
+      Some(&Multivalue::Switch(true)) => { "error" }
+      Some(Multivalue::Text(mode)) if mode == "error" || mode == "warn" => { mode }
+      Some(value) => {
+        output_warning!("Unknown love-guard option {:?}, expected \"error\" or \"warn\"",value);
+        return None;
+      }
+    };
+
+    // anything goes, or a requirement we can't turn into lua
+    let (required,compatible) : (Vec<u32>,String) = match love_requirement(&love.to_string()) {
+      Some(requirement) => { requirement }
+      None => { output_debug!("no love-guard for {}, LOVE {} can't be checked",&self.name,love.to_string()); return None; }
+    };
+
+    let message : String = format!("'{} requires LOVE {}, running ' .. table.concat(running, '.')",&self.name,love);
+    let action : String = if mode == "warn" { format!("print('Warning: ' .. {})",message) } else { format!("error({}, 0)",message) };
+    let required : Vec<String> = required.iter().map(|part| part.to_string()).collect();
+
+    // `order` is how the running version compares to the required parts, `same` how many of them match
+    Some(format!("if love and love.getVersion then
+  local major, minor, revision = love.getVersion()
+  local running, required = {{ major, minor, revision }}, {{ {} }}
+  local order, same = 0, 0
+  for i = 1, #required do
+    if running[i] ~= required[i] then
+      order = running[i] > required[i] and 1 or -1
+      break
+    end
+    same = i
+  end
+  local compatible = {}
+  if not compatible then {} end
+end",required.join(", "),compatible,action))
+  }
+
+}
+
+fn love_requirement(requirement : &str) -> Option<(Vec<u32>,String)> {
+  //! the parts of a love requirement and the lua condition for it, using the `order` and `same` of the guard.
+  //! `None` if any version works.
+  //!
+  //! only the parts that are written down are compared, so `>=0.10` is any version from 0.10.0 on and `=11` is any 11.x.
+  let requirement : String = requirement.chars().filter(|c| !c.is_whitespace()).collect();
+  let operator : &str = ["^", "~", ">=", "<=", ">", "<", "="].iter().find(|operator| requirement.starts_with(*operator)).copied().unwrap_or("");
+
+  // stops at a wildcard, `11.*` is the same as `11`
+  let mut required : Vec<u32> = Vec::new();
+  for part in requirement[operator.len()..].split(".") {
+    if part == "*" || part == "x" || part == "X" { break; }
+    match part.parse::<u32>() { Ok(number) => { required.push(number); } Err(_) => { return None; } }
+  }
+  if required.is_empty() || required.len() > 3 { return None; }
+
+  let condition : String = match operator {
+    ">=" => { "order >= 0".to_string() }
+    ">" => { "order > 0".to_string() }
+    "<=" => { "order <= 0".to_string() }
+    "<" => { "order < 0".to_string() }
+    "=" => { "order == 0".to_string() }
+    // the last written part can go up, `~1.2.3` is 1.2.x and `~1.2` or `~1` stay on that minor or major
+    "~" => { format!("order >= 0 and same >= {}",required.len().min(2)) }
+    // `^` and no operator, everything up to the first part that isn't 0 has to match
+    _ => {
+      let first : usize = required.iter().position(|part| *part > 0).map(|position| position + 1).unwrap_or(required.len());
+      format!("order >= 0 and same >= {}",first.min(required.len()))
+    }
+  };
+
+  Some((required,condition))
+}
+
+fn lua_string(text : &str) -> String {
+  format!("'{}'",text.replace("\\","\\\\").replace("'","\\'").replace("\n","\\n"))
+}
+
+fn lua_list(list : &[String]) -> String {
+  format!("{{ {} }}",list.iter().map(|item| lua_string(item)).collect::<Vec<String>>().join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+  use library::luafile;
+  use super::{love_requirement,LibraryDefinition};
+
+  fn some(required : &[u32], condition : &str) -> Option<(Vec<u32>,String)> { Some((required.to_vec(),condition.to_string())) }
+
+  #[test]
+  fn any_version_has_no_guard() {
+    assert_eq!(love_requirement("*"),None);
+    assert_eq!(love_requirement("x"),None);
+    assert_eq!(love_requirement(">=*"),None);
+  }
+
+  #[test]
+  fn comparisons() {
+    // not only 0.10.x
+    assert_eq!(love_requirement(">=0.10"),some(&[0,10],"order >= 0"));
+    assert_eq!(love_requirement(">= 0.10.2"),some(&[0,10,2],"order >= 0"));
+    assert_eq!(love_requirement("<11"),some(&[11],"order < 0"));
+    assert_eq!(love_requirement(">0.9"),some(&[0,9],"order > 0"));
+    assert_eq!(love_requirement("<=11.1"),some(&[11,1],"order <= 0"));
+    assert_eq!(love_requirement("=11.*"),some(&[11],"order == 0"));
+  }
+
+  #[test]
+  fn caret_and_tilde() {
+    assert_eq!(love_requirement("^11.1"),some(&[11,1],"order >= 0 and same >= 1"));
+    assert_eq!(love_requirement("11.1"),some(&[11,1],"order >= 0 and same >= 1"));
+    assert_eq!(love_requirement("^0.10.2"),some(&[0,10,2],"order >= 0 and same >= 2"));
+    assert_eq!(love_requirement("^0.0.3"),some(&[0,0,3],"order >= 0 and same >= 3"));
+    assert_eq!(love_requirement("^0"),some(&[0],"order >= 0 and same >= 1"));
+
+    assert_eq!(love_requirement("~11.1.2"),some(&[11,1,2],"order >= 0 and same >= 2"));
+    assert_eq!(love_requirement("~11"),some(&[11],"order >= 0 and same >= 1"));
+  }
+
+  #[test]
+  fn not_a_version() {
+    assert_eq!(love_requirement(">=eleven"),None);
+    assert_eq!(love_requirement("1.2.3.4"),None);
+  }
+
+  fn with_profiles(profiles : &str) -> LibraryDefinition {
+    luafile::parse_lualib_settings(&format!("name = \"a\"\nuser = \"u\"\nauthor = \"u\"\nversion = \"1.0.0\"\n{}",profiles)).unwrap()
+  }
+
+  #[test]
+  fn profiles_inherit() {
+    let definition = with_profiles("
+      [profile.base]
+      debug-build = true
+      output = \"out\"
+      [profile.base.defines]
+      LEVEL = 1
+      MODE = \"base\"
+
+      [profile.middle]
+      inherits = \"base\"
+      minify = false
+      [profile.middle.defines]
+      MODE = \"middle\"
+
+      [profile.top]
+      inherits = \"middle\"
+      output = \"top\"
+
+      # starts from the built in release
+      [profile.release]
+      output = \"dist\"
+    ");
+
+    let top = definition.get_profile("top").unwrap();
+    assert_eq!(top.debug_build,Some(true));
+    assert_eq!(top.minify,Some(false));
+    assert_eq!(top.output,Some("top".to_string()));
+    let defines = top.defines.unwrap();
+    assert_eq!(defines["LEVEL"].as_integer(),Some(1));
+    assert_eq!(defines["MODE"].as_str(),Some("middle"));
+
+    let release = definition.get_profile("release").unwrap();
+    assert_eq!((release.minify,release.remove_comments,release.output),(Some(true),Some(true),Some("dist".to_string())));
+
+    assert_eq!(definition.get_profile("dev").unwrap().minify,None);
+    assert!(definition.get_profile("missing").is_err());
+  }
+
+  #[test]
+  fn profile_loops() {
+    let definition = with_profiles("
+      [profile.a]
+      inherits = \"b\"
+      [profile.b]
+      inherits = \"c\"
+      [profile.c]
+      inherits = \"a\"
+      [profile.self]
+      inherits = \"self\"
+      [profile.lost]
+      inherits = \"nowhere\"
+    ");
+
+    assert_eq!(definition.get_profile("a").err(),Some("profile a inherits itself: a -> b -> c -> a".to_string()));
+    assert!(definition.get_profile("self").is_err());
+    assert!(definition.get_profile("lost").is_err());
+  }
+
+  #[test]
+  fn dependency_entries() {
+    let definition = with_profiles("
+      [dependencies]
+      plain = { version = \"1.0\" }
+      renamed = { name = \"other\", path = \"../other\", features = [\"log\", \"ui\"], default-features = false }
+    ");
+    let dependencies = definition.dependencies.unwrap();
+
+    let plain = &dependencies["plain"];
+    assert_eq!(plain.library_name("plain"),"plain");
+    assert_eq!(plain.version,Some("1.0".to_string()));
+    let request = plain.feature_request();
+    assert!(request.features.is_empty());
+    assert!(!request.no_default_features);
+
+    let renamed = &dependencies["renamed"];
+    assert_eq!(renamed.library_name("renamed"),"other");
+    assert_eq!(renamed.path,Some("../other".to_string()));
+    let request = renamed.feature_request();
+    assert_eq!(request.features,vec!["log".to_string(),"ui".to_string()]);
+    assert!(request.no_default_features);
+  }
+}
@@ -1,3 +1,5 @@
 pub mod luafile;
 pub mod lualibdef;
-pub mod multivalue;
\ No newline at end of file
+pub mod multivalue;
+pub mod spdx;
+pub mod workspace;
\ No newline at end of file
@@ -1,4 +1,4 @@
-#[derive(Deserialize,Debug)]
+#[derive(Deserialize,Debug,Clone)]
 #[serde(untagged)]
 pub enum Multivalue {
   Array(Vec<Multivalue>),
@@ -0,0 +1,102 @@
+//! checks `license` fields, which are SPDX license expressions like `MIT` or `(MIT OR Apache-2.0)`.
+
+// the common ones, anything else is only a warning since the full list is always growing.
+static KNOWN_LICENSES : [&str; 36] = [
+  "0BSD", "AFL-3.0", "AGPL-3.0", "AGPL-3.0-only", "AGPL-3.0-or-later", "Apache-2.0", "Artistic-2.0", "BSD-2-Clause",
+  "BSD-3-Clause", "BSL-1.0", "CC-BY-4.0", "CC-BY-SA-4.0", "CC0-1.0", "EPL-2.0", "GPL-2.0", "GPL-2.0-only",
+  "GPL-2.0-or-later", "GPL-3.0", "GPL-3.0-only", "GPL-3.0-or-later", "ISC", "LGPL-2.1", "LGPL-2.1-only", "LGPL-2.1-or-later",
+  "LGPL-3.0", "LGPL-3.0-only", "LGPL-3.0-or-later", "MIT", "MIT-0", "MPL-2.0", "OFL-1.1", "Unlicense",
+  "WTFPL", "Zlib", "zlib-acknowledgement", "Beerware",
+];
+
+pub fn validate(expression : &str) -> Result<Vec<String>,String> {
+  //! checks the expression is well formed, returns the license ids in it that aren't known.
+  let spaced : String = expression.replace("("," ( ").replace(")"," ) ");
+  let tokens : Vec<&str> = spaced.split_whitespace().collect();
+  if tokens.is_empty() { return Err("the license is empty".to_string()); }
+
+  let mut unknown : Vec<String> = Vec::new();
+  let mut depth : usize = 0;
+  // if the next token should be a license (or an opening bracket), otherwise an operator.
+  let mut expect_license : bool = true;
+  let mut previous : &str = "";
+  // if the last license was the exception of a `WITH`
+  let mut exception : bool = false;
+
+  for token in tokens.iter() {
+    match *token {
+      "(" => {
+        if !expect_license { return Err(format!("unexpected `(` after {}",previous)); }
+        if previous == "WITH" { return Err("expected an exception after WITH".to_string()); }
+        depth += 1;
+      }
+      ")" => {
+        if expect_license || depth == 0 { return Err("unexpected `)`".to_string()); }
+        depth -= 1;
+      }
+      "AND" | "OR" | "WITH" => {
+        if expect_license { return Err(format!("expected a license before {}",token)); }
+        // an exception is for one license, not a group of them or another exception
+        if *token == "WITH" && (previous == ")" || exception) { return Err(format!("WITH has to come after a single license, not {}",previous)); }
+        expect_license = true;
+      }
+      license => {
+        if !expect_license { return Err(format!("expected AND, OR or WITH between {} and {}",previous,license)); }
+        if !license.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '+') {
+          return Err(format!("{} isn't a valid license id",license));
+        }
+        // exceptions after `WITH` aren't licenses
+        let id : &str = license.trim_end_matches('+');
+        exception = previous == "WITH";
+        if !exception && !id.starts_with("LicenseRef-") && !KNOWN_LICENSES.contains(&id) { unknown.push(license.to_string()); }
+        expect_license = false;
+      }
+    }
+    previous = token;
+  }
+
+  if expect_license { return Err(format!("expected a license after {}",previous)); }
+  if depth > 0 { return Err("missing `)`".to_string()); }
+
+  Ok(unknown)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::validate;
+
+  fn no_unknown() -> Result<Vec<String>,String> { Ok(Vec::new()) }
+
+  #[test]
+  fn expressions() {
+    assert_eq!(validate("MIT"),no_unknown());
+    assert_eq!(validate("MIT OR Apache-2.0"),no_unknown());
+    assert_eq!(validate("(MIT OR Apache-2.0) AND Zlib"),no_unknown());
+    assert_eq!(validate("GPL-2.0+ WITH Classpath-exception-2.0"),no_unknown());
+    assert_eq!(validate("MIT AND (BSD-3-Clause OR (Apache-2.0 WITH LLVM-exception))"),no_unknown());
+    assert_eq!(validate("LicenseRef-my-own OR MIT"),no_unknown());
+  }
+
+  #[test]
+  fn unknown_ids() {
+    assert_eq!(validate("MIT OR Made-Up-1.0"),Ok(vec!["Made-Up-1.0".to_string()]));
+    assert_eq!(validate("(Nope AND MIT) OR Never WITH Some-exception"),Ok(vec!["Nope".to_string(),"Never".to_string()]));
+  }
+
+  #[test]
+  fn malformed() {
+    for expression in ["", "  ", "MIT Zlib", "MIT AND", "OR MIT", "MIT AND OR Zlib", "(MIT", "MIT)", "()", "MIT (Zlib)", "MIT/X11", "WITH"].iter() {
+      assert!(validate(expression).is_err(),"`{}` should be an error",expression);
+    }
+  }
+
+  #[test]
+  fn with_after_one_license() {
+    // the exception only goes with a single license
+    for expression in ["(MIT AND Zlib) WITH Some-exception", "(GPL-2.0) WITH Classpath-exception-2.0", "GPL-2.0 WITH A-exception WITH B-exception", "GPL-2.0 WITH (Classpath-exception-2.0)"].iter() {
+      assert!(validate(expression).is_err(),"`{}` should be an error",expression);
+    }
+    assert_eq!(validate("MIT AND GPL-2.0 WITH Classpath-exception-2.0"),no_unknown());
+    assert_eq!(validate("(GPL-2.0 WITH Classpath-exception-2.0) OR MIT"),no_unknown());
+  }
+}
@@ -0,0 +1,151 @@
+//! `lmake-workspace.toml`, a folder with more than one library in it.
+//!
+//! the workspace lists the folders of its `members`, and can have `profile`s and an `output` folder that
+//! all the members use.
+//!
+//! every library loaded looks for its workspace, so each workspace file is only read once.
+
+use ansi_term::Colour::{Yellow,Red};
+use toml;
+
+use std::path::{Path,PathBuf};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use {LIBDEFFILE,WORKSPACEFILE};
+use library::luafile;
+use library::lualibdef::Profile;
+use processing;
+
+// the workspaces that were already loaded, by folder
+static WORKSPACES : Mutex<Vec<(PathBuf,Option<Workspace>)>> = Mutex::new(Vec::new());
+
+#[derive(Deserialize,Clone)]
+pub struct Workspace {
+  pub members : Vec<String>,
+  pub output : Option<String>,
+  pub profile : Option<HashMap<String,Profile>>,
+
+  // the folder with the workspace file
+  #[serde(skip_deserializing)]
+  pub root : PathBuf,
+  // the folder of each member by library name, and the other members it depends on
+  #[serde(skip_deserializing)]
+  libraries : HashMap<String,PathBuf>,
+  #[serde(skip_deserializing)]
+  dependencies : HashMap<String,Vec<String>>,
+}
+
+impl Workspace {
+  pub fn member_paths(&self) -> Vec<PathBuf> {
+    self.members.iter().map(|member| { let mut path : PathBuf = self.root.clone(); path.push(member); path }).collect()
+  }
+
+  pub fn output_path(&self) -> Option<PathBuf> {
+    //! the shared output folder, relative to the workspace.
+    self.output.as_ref().map(|output| { let mut path : PathBuf = self.root.clone(); path.push(output); path })
+  }
+
+  pub fn find_member(&self, library_name : &str) -> Option<PathBuf> {
+    //! the folder of the member library called `library_name`.
+    self.libraries.get(library_name).cloned()
+  }
+
+  pub fn member_cycles(&self) -> Vec<Vec<String>> {
+    //! the members that depend on each other, each one is the chain of names starting and ending with the same member.
+    let names : Vec<String> = self.libraries.keys().cloned().collect();
+    processing::order::find_cycles(&names,&self.dependencies)
+  }
+
+  fn read_members(&mut self) {
+    //! reads the name and the dependencies of every member, the first member with a name wins.
+    let mut dependencies : HashMap<String,Vec<String>> = HashMap::new();
+    for path in self.member_paths() {
+      let mut lib_file : PathBuf = path.clone();
+      lib_file.push(LIBDEFFILE);
+      if !lib_file.exists() { continue; }
+
+      if let Some(definition) = luafile::parse_lualib_settings(&luafile::get_raw_file_contents(&lib_file)) {
+        if self.libraries.contains_key(&definition.name) {
+          output_warning!("More than one member of the workspace is called {}, using {}",Yellow.paint(definition.name.clone()),self.libraries[&definition.name].display().to_string());
+          continue;
+        }
+        let names : Vec<String> = definition.dependencies.as_ref()
+          .map(|hash| hash.iter().map(|(key,dependency)| dependency.library_name(key)).collect())
+          .unwrap_or(Vec::new());
+        self.libraries.insert(definition.name.clone(),path);
+        dependencies.insert(definition.name,names);
+      }
+    }
+
+    // only the dependencies that are members
+    for (name,names) in dependencies {
+      let members : Vec<String> = names.into_iter().filter(|dependency| self.libraries.contains_key(dependency)).collect();
+      self.dependencies.insert(name,members);
+    }
+  }
+}
+
+pub fn load(path : &PathBuf) -> Option<Workspace> {
+  //! loads the workspace file in `path`, if there is one.
+  if let Some((_,workspace)) = WORKSPACES.lock().unwrap().iter().find(|&(folder,_)| folder == path) { return workspace.clone(); }
+
+  let mut workspace_file : PathBuf = path.clone();
+  workspace_file.push(WORKSPACEFILE);
+  let workspace : Option<Workspace> = if !workspace_file.exists() { None } else {
+    match toml::from_str::<Workspace>(&luafile::get_raw_file_contents(&workspace_file)) {
+      Err(error) => {
+        output_error!("Error parsing the workspace file \'{}\': {}",Yellow.paint(workspace_file.display().to_string()),Red.paint(error.to_string()));
+        None
+      }
+      Ok(mut workspace) => {
+        workspace.root = path.clone();
+        workspace.read_members();
+        Some(workspace)
+      }
+    }
+  };
+
+  WORKSPACES.lock().unwrap().push((path.clone(),workspace.clone()));
+  workspace
+}
+
+pub fn containing(library_path : &Path) -> Option<Workspace> {
+  //! the workspace that has the library as a member, looking in the library's folder and the ones above it.
+  let library_path : PathBuf = match library_path.canonicalize() { Ok(path) => { path } Err(_) => { return None; } };
+
+  for folder in library_path.ancestors() {
+    if let Some(workspace) = load(&folder.to_path_buf()) {
+      if workspace.member_paths().iter().any(|member| member.canonicalize().ok().as_ref() == Some(&library_path)) { return Some(workspace); }
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use toml;
+  use std::path::PathBuf;
+  use super::Workspace;
+
+  fn workspace(members : &[(&str,&[&str])]) -> Workspace {
+    let mut workspace : Workspace = toml::from_str("members = []").unwrap();
+    for &(name,dependencies) in members.iter() {
+      workspace.libraries.insert(name.to_string(),PathBuf::from(name));
+      workspace.dependencies.insert(name.to_string(),dependencies.iter().map(|dependency| dependency.to_string()).collect());
+    }
+    workspace
+  }
+
+  #[test]
+  fn member_cycles() {
+    assert!(workspace(&[("graphs",&["nodes"]),("nodes",&[]),("shapes",&["graphs","nodes"])]).member_cycles().is_empty());
+
+    let cycles : Vec<Vec<String>> = workspace(&[("a",&["b"]),("b",&["c"]),("c",&["a"]),("d",&[])]).member_cycles();
+    assert_eq!(cycles,vec![vec!["a".to_string(),"b".to_string(),"c".to_string(),"a".to_string()]]);
+
+    assert_eq!(workspace(&[("a",&["a"])]).member_cycles().len(),1);
+    assert_eq!(workspace(&[("a",&["b"])]).find_member("a"),Some(PathBuf::from("a")));
+    assert_eq!(workspace(&[("a",&["b"])]).find_member("b"),None);
+  }
+}
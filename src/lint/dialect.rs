@@ -0,0 +1,229 @@
+//! finds syntax and standard library functions that don't exist in the lua the library targets.
+//!
+//! the target is `target-lua` in the definition, LÖVE uses LuaJIT so that is the default.
+
+use ansi_term::Colour::{Red,Yellow,Blue};
+
+use std::path::{Path,PathBuf};
+use std::fmt;
+
+use library;
+use library::lualibdef::LibraryDefinition;
+use processing::compile::require_path;
+use processing::lexer::{self,Token,TokenKind};
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum Dialect { Lua51, LuaJIT, Lua52, Lua53, Lua54 }
+
+impl Dialect {
+  pub fn from_str(text : &str) -> Option<Dialect> {
+    match text.to_lowercase().as_str() {
+      "5.1" => { Some(Dialect::Lua51) }
+      "luajit" | "jit" => { Some(Dialect::LuaJIT) }
+      "5.2" => { Some(Dialect::Lua52) }
+      "5.3" => { Some(Dialect::Lua53) }
+      "5.4" => { Some(Dialect::Lua54) }
+      _ => { None }
+    }
+  }
+}
+
+impl fmt::Display for Dialect {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    write!(f,"{}",match *self {
+      Dialect::Lua51 => { "Lua 5.1" }
+      Dialect::LuaJIT => { "LuaJIT" }
+      Dialect::Lua52 => { "Lua 5.2" }
+      Dialect::Lua53 => { "Lua 5.3" }
+      Dialect::Lua54 => { "Lua 5.4" }
+    })
+  }
+}
+
+use self::Dialect::*;
+
+struct Rule {
+  name : &'static str,
+  dialects : &'static [Dialect],
+  note : &'static str,
+}
+
+// syntax, matched against symbols and keywords.
+static SYNTAX : [Rule; 8] = [
+  Rule { name : "goto", dialects : &[LuaJIT,Lua52,Lua53,Lua54], note : "" },
+  Rule { name : "::", dialects : &[LuaJIT,Lua52,Lua53,Lua54], note : "labels" },
+  Rule { name : "//", dialects : &[Lua53,Lua54], note : "integer division, use math.floor(a / b)" },
+  Rule { name : "&", dialects : &[Lua53,Lua54], note : "bitwise and" },
+  Rule { name : "|", dialects : &[Lua53,Lua54], note : "bitwise or" },
+  Rule { name : "~", dialects : &[Lua53,Lua54], note : "bitwise xor / not" },
+  Rule { name : "<<", dialects : &[Lua53,Lua54], note : "bit shift" },
+  Rule { name : ">>", dialects : &[Lua53,Lua54], note : "bit shift" },
+];
+
+// standard library, a table name (`bit`) covers everything inside of it.
+static LIBRARY : [Rule; 16] = [
+  Rule { name : "unpack", dialects : &[Lua51,LuaJIT], note : "use table.unpack" },
+  Rule { name : "table.unpack", dialects : &[Lua52,Lua53,Lua54], note : "use unpack" },
+  Rule { name : "table.pack", dialects : &[Lua52,Lua53,Lua54], note : "use {...} and select('#', ...)" },
+  Rule { name : "setfenv", dialects : &[Lua51,LuaJIT], note : "use _ENV" },
+  Rule { name : "getfenv", dialects : &[Lua51,LuaJIT], note : "use _ENV" },
+  Rule { name : "loadstring", dialects : &[Lua51,LuaJIT], note : "use load" },
+  Rule { name : "module", dialects : &[Lua51,LuaJIT], note : "return a table instead" },
+  Rule { name : "_ENV", dialects : &[Lua52,Lua53,Lua54], note : "" },
+  Rule { name : "bit", dialects : &[LuaJIT], note : "" },
+  Rule { name : "bit32", dialects : &[Lua52], note : "" },
+  Rule { name : "jit", dialects : &[LuaJIT], note : "" },
+  Rule { name : "utf8", dialects : &[Lua53,Lua54], note : "" },
+  Rule { name : "string.pack", dialects : &[Lua53,Lua54], note : "" },
+  Rule { name : "string.unpack", dialects : &[Lua53,Lua54], note : "" },
+  Rule { name : "math.tointeger", dialects : &[Lua53,Lua54], note : "" },
+  Rule { name : "math.type", dialects : &[Lua53,Lua54], note : "" },
+];
+
+pub struct DialectProblem {
+  pub name : String,
+  pub note : &'static str,
+  pub file : PathBuf,
+  pub line : usize,
+}
+
+impl DialectProblem {
+  pub fn to_string(&self, dialect : &Dialect) -> String {
+    let mut text : String = format!("{} isn't available in {} in {}:{}",Red.paint(self.name.clone()),dialect,Yellow.paint(self.file.display().to_string()),self.line);
+    if !self.note.is_empty() { text = format!("{} ({})",text,self.note); }
+    text
+  }
+}
+
+pub fn target(definition : &LibraryDefinition) -> Option<Dialect> {
+  //! the lua the library is written for, `None` if `target-lua` isn't one we know.
+  match definition.target_lua {
+    None => { Some(Dialect::LuaJIT) }
+    Some(ref target) => { Dialect::from_str(target) }
+  }
+}
+
+pub fn check_library(path : &Path, definition : &LibraryDefinition) -> bool {
+  //! warns about everything that won't work in the targeted lua. Returns false if the target isn't valid.
+  let dialect : Dialect = match target(definition) {
+    Some(dialect) => { dialect }
+    None => {
+      output_error!("Unknown target-lua {}, expected one of 5.1, luajit, 5.2, 5.3 or 5.4",Red.paint(definition.target_lua.clone().unwrap_or_default()));
+      return false;
+    }
+  };
+  output_debug!("Checking {} against {}",Blue.paint(definition.name.clone()),dialect.to_string());
+
+  if let Some(references) = definition.references() {
+    let mut files : Vec<String> = references.values().cloned().collect();
+    files.sort();
+    files.dedup();
+
+    for file in files {
+      let src_path = require_path(path,&file);
+      let contents : String = library::luafile::get_raw_file_contents(&src_path);
+      for problem in find_problems(&contents,&src_path,&dialect) { output_warning!("{}",problem.to_string(&dialect)); }
+    }
+  }
+
+  true
+}
+
+pub fn find_problems(contents : &str, src_path : &Path, dialect : &Dialect) -> Vec<DialectProblem> {
+  let tokens : Vec<Token> = match lexer::tokenize(contents) {
+    Ok(tokens) => { tokens.into_iter().filter(|token| token.kind != TokenKind::Comment).collect() }
+    Err(_) => { return Vec::new(); }
+  };
+  let text = |index : usize| -> &str { if index < tokens.len() { &tokens[index].text } else { "" } };
+
+  let mut problems : Vec<DialectProblem> = Vec::new();
+  let mut add = |name : &str, rule : &Rule, line : usize| {
+    if rule.dialects.contains(dialect) { return; }
+    problems.push(DialectProblem { name : name.to_string(), note : rule.note, file : src_path.to_path_buf(), line });
+  };
+
+  let mut i : usize = 0;
+  while i < tokens.len() {
+    let token : &Token = &tokens[i];
+
+    match token.kind {
+      TokenKind::Symbol | TokenKind::Keyword => {
+        if let Some(rule) = SYNTAX.iter().find(|rule| rule.name == token.text) { add(&token.text,rule,token.line); }
+        // only reports the opening `::` of a label
+        if token.text == "::" { i += 2; }
+      }
+
+      TokenKind::Name => {
+        let after_field : bool = i > 0 && (text(i - 1) == "." || text(i - 1) == ":");
+        let start : usize = i;
+        let mut name : String = token.text.clone();
+        while text(i + 1) == "." && i + 2 < tokens.len() && tokens[i + 2].kind == TokenKind::Name {
+          name = format!("{}.{}",name,tokens[i + 2].text);
+          i += 2;
+        }
+
+        // `local unpack = unpack or table.unpack` is how you write for more than one version
+        let fallback : bool = text(i + 1) == "or" || (start > 0 && text(start - 1) == "or");
+        // `{ module = .. }` and `local utf8 = ..` are just names
+        let declared : bool = text(i + 1) == "=" && start > 0 && (text(start - 1) == "local" || text(start - 1) == "{" || text(start - 1) == ",");
+
+        if !after_field && !fallback && !declared {
+          let rule : Option<&Rule> = LIBRARY.iter().find(|rule| name == rule.name || name.starts_with(&format!("{}.",rule.name)));
+          if let Some(rule) = rule { add(rule.name,rule,token.line); }
+        }
+      }
+
+      _ => { }
+    }
+
+    i += 1;
+  }
+
+  problems
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use super::{find_problems,Dialect};
+
+  fn names(source : &str, dialect : Dialect) -> Vec<String> {
+    find_problems(source,&PathBuf::from("main.lua"),&dialect).into_iter().map(|problem| problem.name).collect()
+  }
+
+  #[test]
+  fn from_str() {
+    assert_eq!(Dialect::from_str("5.1"),Some(Dialect::Lua51));
+    assert_eq!(Dialect::from_str("LuaJIT"),Some(Dialect::LuaJIT));
+    assert_eq!(Dialect::from_str("jit"),Some(Dialect::LuaJIT));
+    assert_eq!(Dialect::from_str("5.4"),Some(Dialect::Lua54));
+    assert_eq!(Dialect::from_str("5.5"),None);
+  }
+
+  #[test]
+  fn syntax() {
+    let source : &str = "local a = 7 // 2\nlocal b = a & 1 | 2 ~ 3\n::top::\ngoto top";
+    assert_eq!(names(source,Dialect::Lua51),vec!["//","&","|","~","::","goto"]);
+    assert_eq!(names(source,Dialect::LuaJIT),vec!["//","&","|","~"]);
+    assert!(names(source,Dialect::Lua53).is_empty());
+
+    // `~=` isn't `~`
+    assert!(names("if a ~= b then end",Dialect::Lua51).is_empty());
+  }
+
+  #[test]
+  fn library() {
+    let source : &str = "local x = unpack(t)\nlocal y = table.unpack(t)\nlocal n = bit.band(1, 2)\nlocal c = utf8.char(72)";
+    assert_eq!(names(source,Dialect::LuaJIT),vec!["table.unpack","utf8"]);
+    assert_eq!(names(source,Dialect::Lua53),vec!["unpack","bit"]);
+    assert_eq!(names(source,Dialect::Lua52),vec!["unpack","bit","utf8"]);
+  }
+
+  #[test]
+  fn written_for_more_than_one() {
+    let source : &str = "local unpack = unpack or table.unpack\nlocal t = { module = 1 }\nlocal s = obj.setfenv\n-- loadstring";
+    for dialect in [Dialect::Lua51,Dialect::LuaJIT,Dialect::Lua52,Dialect::Lua53,Dialect::Lua54].iter() {
+      assert!(names(source,*dialect).is_empty(),"{}",dialect.to_string());
+    }
+  }
+}
@@ -0,0 +1,330 @@
+//! finds globals being set or read in the library's source files.
+//!
+//! everything in a compiled library shares the same environment, so a missing `local` leaks into every
+//! other library and the game. Tracks the `local` names of each block, anything else that isn't a known
+//! global (lua's standard library, LÖVE, or the `globals` list in the definition) is reported.
+
+use ansi_term::Colour::{Red,Yellow};
+
+use std::path::{Path,PathBuf};
+use std::fmt;
+use std::mem;
+
+use library;
+use library::lualibdef::LibraryDefinition;
+use processing::compile::require_path;
+use processing::lexer::{self,Token,TokenKind};
+
+static KNOWN_GLOBALS : [&str; 45] = [
+  "_G", "_VERSION", "_ENV", "arg", "assert", "collectgarbage", "dofile", "error", "getfenv", "getmetatable",
+  "ipairs", "load", "loadfile", "loadstring", "module", "next", "pairs", "pcall", "print", "rawequal",
+  "rawget", "rawlen", "rawset", "require", "select", "setfenv", "setmetatable", "tonumber", "tostring", "type",
+  "unpack", "xpcall", "coroutine", "debug", "io", "math", "os", "package", "string", "table",
+  "utf8", "bit", "bit32", "jit", "love",
+];
+
+// the symbols that can come after a value in the same expression
+static CONTINUING_SYMBOLS : [&str; 24] = [
+  "+", "-", "*", "/", "//", "%", "^", "..", "==", "~=", "<", "<=", ">", ">=", "&", "|", "~", "<<", ">>",
+  ".", ":", "(", "[", "{",
+];
+
+#[derive(PartialEq)]
+pub enum Usage { Assigned, Read }
+
+pub struct GlobalUsage {
+  pub name : String,
+  pub usage : Usage,
+  pub file : PathBuf,
+  pub line : usize,
+}
+
+impl fmt::Display for GlobalUsage {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    let action : &str = match self.usage { Usage::Assigned => { "Assignment to global" } Usage::Read => { "Read of unknown global" } };
+    write!(f,"{} {} in {}:{}",action,Red.paint(self.name.clone()),Yellow.paint(self.file.display().to_string()),self.line)
+  }
+}
+
+pub fn check_library(path : &Path, definition : &LibraryDefinition, deny : bool) -> bool {
+  //! shows the globals used in every require and internal module. Returns false if there were any
+  //! and globals aren't allowed with `--deny-globals`.
+  let mut found : Vec<GlobalUsage> = Vec::new();
+
+  if let Some(references) = definition.references() {
+    let mut files : Vec<String> = references.values().cloned().collect();
+    files.sort();
+    files.dedup();
+
+    for file in files {
+      let src_path = require_path(path,&file);
+      let contents : String = library::luafile::get_raw_file_contents(&src_path);
+      found.extend(find_globals(&contents,&src_path,&definition.globals));
+    }
+  }
+
+  for usage in found.iter() {
+    if deny { output_error!("{}",usage.to_string()); }
+    else { output_warning!("{}",usage.to_string()); }
+  }
+
+  !deny || found.is_empty()
+}
+
+pub fn find_globals(contents : &str, src_path : &Path, allowed : &Option<Vec<String>>) -> Vec<GlobalUsage> {
+  //! each global is only reported once per file and usage, on the first line it shows up.
+  let tokens : Vec<Token> = match lexer::tokenize(contents) {
+    Ok(tokens) => { tokens.into_iter().filter(|token| token.kind != TokenKind::Comment).collect() }
+    Err(_) => { return Vec::new(); }
+  };
+
+  let mut found : Vec<GlobalUsage> = Vec::new();
+  let mut scopes : Vec<Vec<String>> = vec![Vec::new()];
+  let mut brackets : Vec<&str> = Vec::new();
+  // the `do` after a `for` doesn't start a new scope, the `for` already did.
+  let mut loop_scope : bool = false;
+  // the `repeat` scope lasts until the end of the `until` condition, so its locals can be used there.
+  // these are the brackets and scopes that were open at the `until`.
+  let mut conditions : Vec<(usize,usize)> = Vec::new();
+  // `local a = a` reads the `a` from outside, so the names are only declared once the expression after
+  // the `=` is over, with the brackets and scopes that were open at the `=`.
+  let mut pending : Vec<(Vec<String>,usize,usize,usize)> = Vec::new();
+  // a function body is its own statement even inside of a call, these are the brackets from outside of it
+  // and the scope depth of the body.
+  let mut functions : Vec<(usize,Vec<&str>)> = Vec::new();
+
+  let text = |index : usize| -> &str { if index < tokens.len() { &tokens[index].text } else { "" } };
+
+  let mut i : usize = 0;
+  while i < tokens.len() {
+    let token = &tokens[i];
+
+    while let Some(&(open_brackets,open_scopes)) = conditions.last() {
+      if brackets.len() != open_brackets || scopes.len() != open_scopes || !expression_ended(&tokens,i) { break; }
+      conditions.pop();
+      if scopes.len() > 1 { scopes.pop(); }
+    }
+
+    while let Some(&(_,equals,open_brackets,open_scopes)) = pending.last() {
+      if i <= equals || brackets.len() != open_brackets || scopes.len() != open_scopes || text(i) == "," || !expression_ended(&tokens,i) { break; }
+      if let Some((names,_,_,_)) = pending.pop() { for name in names.iter() { declare(&mut scopes,name); } }
+    }
+
+    match (token.kind,token.text.as_str()) {
+      (TokenKind::Symbol,"{") | (TokenKind::Symbol,"(") | (TokenKind::Symbol,"[") => { brackets.push(&token.text); }
+      (TokenKind::Symbol,"}") | (TokenKind::Symbol,")") | (TokenKind::Symbol,"]") => { brackets.pop(); }
+
+      (TokenKind::Keyword,"local") => {
+        if text(i + 1) == "function" {
+          // `local function name` can call itself, so the name is declared before the body
+          if i + 2 < tokens.len() { declare(&mut scopes,&tokens[i + 2].text); }
+          i += 1;
+          continue;
+        }
+        // `local a, b, c`
+        let mut names : Vec<String> = Vec::new();
+        i += 1;
+        while i < tokens.len() && tokens[i].kind == TokenKind::Name {
+          names.push(tokens[i].text.clone());
+          // skips attributes like `<const>`
+          if text(i + 1) == "<" { i += 3; }
+          if text(i + 1) != "," { break; }
+          i += 2;
+        }
+        if text(i + 1) == "=" { pending.push((names,i + 1,brackets.len(),scopes.len())); }
+        else { for name in names.iter() { declare(&mut scopes,name); } }
+      }
+
+      (TokenKind::Keyword,"function") => {
+        // the name of the function, `function name()` sets a global unless it is already local
+        let mut j : usize = i + 1;
+        let mut method : bool = false;
+        if j < tokens.len() && tokens[j].kind == TokenKind::Name && !(i > 0 && text(i - 1) == "local") {
+          let name : &Token = &tokens[j];
+          let is_field : bool = text(j + 1) == "." || text(j + 1) == ":";
+          if !is_declared(&scopes,&name.text) && !is_allowed(&name.text,allowed) {
+            add(&mut found,&name.text,if is_field { Usage::Read } else { Usage::Assigned },src_path,name.line);
+          }
+          j += 1;
+          while text(j) == "." || text(j) == ":" { if text(j) == ":" { method = true; } j += 2; }
+        } else if j < tokens.len() && tokens[j].kind == TokenKind::Name { j += 1; }
+
+        scopes.push(Vec::new());
+        functions.push((scopes.len(),mem::take(&mut brackets)));
+        if method { declare(&mut scopes,"self"); }
+
+        // the parameters
+        if text(j) == "(" {
+          j += 1;
+          while j < tokens.len() && text(j) != ")" {
+            if tokens[j].kind == TokenKind::Name { declare(&mut scopes,&tokens[j].text); }
+            j += 1;
+          }
+        }
+        i = j + 1;
+        continue;
+      }
+
+      (TokenKind::Keyword,"for") => {
+        scopes.push(Vec::new());
+        loop_scope = true;
+        // `for i = ..` or `for k, v in ..`
+        i += 1;
+        while i < tokens.len() && tokens[i].kind == TokenKind::Name {
+          declare(&mut scopes,&tokens[i].text);
+          if text(i + 1) != "," { break; }
+          i += 2;
+        }
+      }
+      (TokenKind::Keyword,"do") => { if loop_scope { loop_scope = false; } else { scopes.push(Vec::new()); } }
+      (TokenKind::Keyword,"if") | (TokenKind::Keyword,"repeat") => { scopes.push(Vec::new()); }
+      (TokenKind::Keyword,"elseif") | (TokenKind::Keyword,"else") => { scopes.pop(); scopes.push(Vec::new()); }
+      (TokenKind::Keyword,"end") if scopes.len() > 1 => {
+        if functions.last().map(|&(depth,_)| depth) == Some(scopes.len()) {
+          if let Some((_,outside)) = functions.pop() { brackets = outside; }
+        }
+        scopes.pop();
+      }
+      (TokenKind::Keyword,"until") => { conditions.push((brackets.len(),scopes.len())); }
+
+      // labels and gotos aren't variables
+      (TokenKind::Keyword,"goto") => { i += 1; }
+      (TokenKind::Symbol,"::") => { i += 2; }
+
+      (TokenKind::Name,name) => {
+        let after_field : bool = i > 0 && (text(i - 1) == "." || text(i - 1) == ":");
+        // `$NAME` is a define, it is replaced when compiling
+        let define : bool = i > 0 && text(i - 1) == "$" && tokens[i - 1].end == token.start;
+        let table_key : bool = brackets.last() == Some(&"{") && text(i + 1) == "=" && i > 0 && (text(i - 1) == "{" || text(i - 1) == "," || text(i - 1) == ";");
+
+        if !after_field && !define && !table_key && !is_declared(&scopes,name) && !is_allowed(name,allowed) {
+          let usage : Usage = if brackets.is_empty() && is_assignment(&tokens,i) { Usage::Assigned } else { Usage::Read };
+          add(&mut found,name,usage,src_path,token.line);
+        }
+      }
+
+      _ => { }
+    }
+
+    i += 1;
+  }
+
+  for _ in conditions { if scopes.len() > 1 { scopes.pop(); } }
+
+  found
+}
+
+fn expression_ended(tokens : &[Token], index : usize) -> bool {
+  //! checks if the expression that came before `index` is over, a value followed by something that can't continue it.
+  let previous : &Token = &tokens[index - 1];
+  let value_end : bool = matches!((previous.kind,previous.text.as_str()),
+    (TokenKind::Name,_) | (TokenKind::Number,_) | (TokenKind::String,_) |
+    (TokenKind::Keyword,"true") | (TokenKind::Keyword,"false") | (TokenKind::Keyword,"nil") | (TokenKind::Keyword,"end") |
+    (TokenKind::Symbol,")") | (TokenKind::Symbol,"]") | (TokenKind::Symbol,"}") | (TokenKind::Symbol,"..."));
+  if !value_end { return false; }
+
+  // calls, indexing and operators keep it going
+  let token : &Token = &tokens[index];
+  let continues : bool = match (token.kind,token.text.as_str()) {
+    (TokenKind::String,_) | (TokenKind::Keyword,"and") | (TokenKind::Keyword,"or") => { true }
+    (TokenKind::Symbol,symbol) => { CONTINUING_SYMBOLS.contains(&symbol) }
+    _ => { false }
+  };
+  !continues
+}
+
+fn is_assignment(tokens : &[Token], index : usize) -> bool {
+  //! checks for `name =` or `name, other = ..` starting at `index`.
+  let text = |index : usize| -> &str { if index < tokens.len() { &tokens[index].text } else { "" } };
+
+  if text(index + 1) == "=" { return true; }
+  if text(index + 1) != "," { return false; }
+
+  let mut i : usize = index + 1;
+  while i < tokens.len() {
+    match text(i) {
+      "=" => { return true; }
+      // the other names, can be fields like `other.field`
+      "," | "." if i + 1 < tokens.len() && tokens[i + 1].kind == TokenKind::Name => { i += 2; }
+      _ => { return false; }
+    }
+  }
+  false
+}
+
+fn declare(scopes : &mut [Vec<String>], name : &str) {
+  if let Some(scope) = scopes.last_mut() { scope.push(name.to_string()); }
+}
+
+fn is_declared(scopes : &[Vec<String>], name : &str) -> bool {
+  scopes.iter().any(|scope| scope.iter().any(|declared| declared == name))
+}
+
+fn is_allowed(name : &str, allowed : &Option<Vec<String>>) -> bool {
+  if KNOWN_GLOBALS.contains(&name) { return true; }
+  if let Some(ref allowed) = *allowed { return allowed.iter().any(|global| global == name); }
+  false
+}
+
+fn add(found : &mut Vec<GlobalUsage>, name : &str, usage : Usage, src_path : &Path, line : usize) {
+  if found.iter().any(|existing| existing.name == name && existing.usage == usage) { return; }
+  found.push(GlobalUsage { name : name.to_string(), usage, file : src_path.to_path_buf(), line });
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use super::{find_globals,Usage};
+
+  fn globals(source : &str) -> Vec<String> {
+    find_globals(source,&PathBuf::from("test.lua"),&None).into_iter().map(|usage| usage.name).collect()
+  }
+
+  #[test]
+  fn repeat_locals_in_until() {
+    assert!(globals("repeat local x = f() until x").contains(&"f".to_string()));
+    assert_eq!(globals("local f = next repeat local x = f() until x"),Vec::<String>::new());
+    assert_eq!(globals("local t = {} repeat local x = #t until x > 3 and t[x] or t.done(x)"),Vec::<String>::new());
+
+    // the scope ends with the condition
+    assert_eq!(globals("repeat local x = 1 until x print(x)"),vec!["x"]);
+    assert_eq!(globals("repeat local x = 1 until x == 1 x = 2"),vec!["x"]);
+    assert_eq!(globals("repeat repeat local x = 1 until x local y = x until y"),vec!["x"]);
+    assert_eq!(globals("local function f() repeat local x = 1 until x end return x"),vec!["x"]);
+  }
+
+  #[test]
+  fn local_shadowing() {
+    // the expression is read before the local exists
+    assert_eq!(globals("local a = a"),vec!["a"]);
+    assert_eq!(globals("local log = log log('hi')"),vec!["log"]);
+    assert_eq!(globals("local a, b = 1, a"),vec!["a"]);
+    assert_eq!(globals("local a = function() return a end"),vec!["a"]);
+    assert_eq!(globals("local a = 1 local a = a + 1 return a"),Vec::<String>::new());
+    assert_eq!(globals("local t = { x = 1 } local y = t.x return y"),Vec::<String>::new());
+  }
+
+  #[test]
+  fn nested_functions() {
+    assert_eq!(globals("local function f(a) return function(b) return a + b + c end end"),vec!["c"]);
+    assert_eq!(globals("function f() local x = 1 end return x"),vec!["f","x"]);
+    assert_eq!(globals("local function f() return f() end"),Vec::<String>::new());
+  }
+
+  #[test]
+  fn for_variables() {
+    assert_eq!(globals("for i = 1, 10 do show(i) end"),vec!["show"]);
+    assert_eq!(globals("local t = {} for k, v in each(t) do t[k] = v end"),vec!["each"]);
+    assert_eq!(globals("for i = 1, 2 do end return i"),vec!["i"]);
+  }
+
+  #[test]
+  fn assignments_in_arguments() {
+    let usages = find_globals("try(function() x = 1 end)",&PathBuf::from("test.lua"),&None);
+    assert_eq!(usages.iter().map(|usage| (usage.name.as_str(),usage.usage == Usage::Assigned)).collect::<Vec<(&str,bool)>>(),vec![("try",false),("x",true)]);
+
+    // the brackets outside of the function are back after its `end`
+    let usages = find_globals("local t = { f = function() y = 1 end, z }",&PathBuf::from("test.lua"),&None);
+    assert_eq!(usages.iter().map(|usage| (usage.name.as_str(),usage.usage == Usage::Assigned)).collect::<Vec<(&str,bool)>>(),vec![("y",true),("z",false)]);
+  }
+}
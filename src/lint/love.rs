@@ -0,0 +1,327 @@
+//! checks the `love.*` functions a library uses against the LÖVE version in its definition.
+
+use ansi_term::Colour::{Red,Yellow,Blue};
+
+use std::path::{Path,PathBuf};
+use std::fmt;
+
+use library;
+use library::lualibdef::LibraryDefinition;
+use processing::compile::require_path;
+use processing::lexer::{self,Token,TokenKind};
+
+// checks the arguments of a call, each argument is the list of its tokens
+type ArgumentCheck = fn(&[Vec<&Token>]) -> bool;
+
+struct ApiChange {
+  name : &'static str,
+  added : Option<&'static str>,
+  removed : Option<&'static str>,
+  changed : Option<&'static str>,
+  note : &'static str,
+  // for changed functions, checks the arguments of the call for the old behaviour
+  check : Option<ArgumentCheck>,
+}
+
+// a module (`love.math`) covers everything inside of it.
+static API_CHANGES : [ApiChange; 43] = [
+  ApiChange { name : "love.getVersion", added : Some("0.9.1"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.math", added : Some("0.9.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.system", added : Some("0.9.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.window", added : Some("0.9.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.data", added : Some("11.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.video", added : Some("0.10.0"), removed : None, changed : None, note : "", check : None },
+
+  ApiChange { name : "love.audio.newSource", added : None, removed : None, changed : Some("11.0"), note : "the source type (\"static\" or \"stream\") is required", check : Some(has_one_argument) },
+
+  ApiChange { name : "love.filesystem.enumerate", added : None, removed : Some("0.9.0"), changed : None, note : "use love.filesystem.getDirectoryItems", check : None },
+  ApiChange { name : "love.filesystem.getDirectoryItems", added : Some("0.9.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.filesystem.exists", added : None, removed : Some("11.0"), changed : None, note : "use love.filesystem.getInfo", check : None },
+  ApiChange { name : "love.filesystem.isFile", added : None, removed : Some("11.0"), changed : None, note : "use love.filesystem.getInfo", check : None },
+  ApiChange { name : "love.filesystem.isDirectory", added : None, removed : Some("11.0"), changed : None, note : "use love.filesystem.getInfo", check : None },
+  ApiChange { name : "love.filesystem.isSymlink", added : None, removed : Some("11.0"), changed : None, note : "use love.filesystem.getInfo", check : None },
+  ApiChange { name : "love.filesystem.getLastModified", added : None, removed : Some("11.0"), changed : None, note : "use love.filesystem.getInfo", check : None },
+  ApiChange { name : "love.filesystem.getSize", added : None, removed : Some("11.0"), changed : None, note : "use love.filesystem.getInfo", check : None },
+  ApiChange { name : "love.filesystem.getInfo", added : Some("11.0"), removed : None, changed : None, note : "", check : None },
+
+  ApiChange { name : "love.graphics.setColor", added : None, removed : None, changed : Some("11.0"), note : "colors go from 0 to 1 instead of 0 to 255", check : Some(has_color_above_one) },
+  ApiChange { name : "love.graphics.setBackgroundColor", added : None, removed : None, changed : Some("11.0"), note : "colors go from 0 to 1 instead of 0 to 255", check : Some(has_color_above_one) },
+  ApiChange { name : "love.graphics.clear", added : None, removed : None, changed : Some("11.0"), note : "colors go from 0 to 1 instead of 0 to 255", check : Some(has_color_above_one) },
+  ApiChange { name : "love.graphics.newFramebuffer", added : None, removed : Some("0.8.0"), changed : None, note : "use love.graphics.newCanvas", check : None },
+  ApiChange { name : "love.graphics.drawq", added : None, removed : Some("0.9.0"), changed : None, note : "use love.graphics.draw with a quad", check : None },
+  ApiChange { name : "love.graphics.setColorMode", added : None, removed : Some("0.9.0"), changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.setDefaultImageFilter", added : None, removed : Some("0.9.0"), changed : None, note : "use love.graphics.setDefaultFilter", check : None },
+  ApiChange { name : "love.graphics.setDefaultFilter", added : Some("0.9.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.setMode", added : None, removed : Some("0.9.0"), changed : None, note : "use love.window.setMode", check : None },
+  ApiChange { name : "love.graphics.getMode", added : None, removed : Some("0.9.0"), changed : None, note : "use love.window.getMode", check : None },
+  ApiChange { name : "love.graphics.setCaption", added : None, removed : Some("0.9.0"), changed : None, note : "use love.window.setTitle", check : None },
+  ApiChange { name : "love.graphics.getRendererInfo", added : Some("0.9.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.point", added : None, removed : Some("0.10.0"), changed : None, note : "use love.graphics.points", check : None },
+  ApiChange { name : "love.graphics.points", added : Some("0.10.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.setPointStyle", added : None, removed : Some("0.10.0"), changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.setStencil", added : None, removed : Some("0.10.0"), changed : None, note : "use love.graphics.stencil and love.graphics.setStencilTest", check : None },
+  ApiChange { name : "love.graphics.setInvertedStencil", added : None, removed : Some("0.10.0"), changed : None, note : "use love.graphics.stencil and love.graphics.setStencilTest", check : None },
+  ApiChange { name : "love.graphics.stencil", added : Some("0.10.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.setStencilTest", added : Some("0.10.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.isSupported", added : None, removed : Some("0.10.0"), changed : None, note : "use love.graphics.getSupported", check : None },
+  ApiChange { name : "love.graphics.getSupported", added : Some("0.10.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.newText", added : Some("0.10.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.newScreenshot", added : None, removed : Some("11.0"), changed : None, note : "use love.graphics.captureScreenshot", check : None },
+  ApiChange { name : "love.graphics.captureScreenshot", added : Some("11.0"), removed : None, changed : None, note : "", check : None },
+  ApiChange { name : "love.graphics.applyTransform", added : Some("11.0"), removed : None, changed : None, note : "", check : None },
+
+  ApiChange { name : "love.mouse.setGrab", added : None, removed : Some("0.9.0"), changed : None, note : "use love.mouse.setGrabbed", check : None },
+  ApiChange { name : "love.timer.getMicroTime", added : None, removed : Some("0.9.0"), changed : None, note : "use love.timer.getTime", check : None },
+];
+
+pub struct ApiProblem {
+  pub function : String,
+  pub message : String,
+  pub file : PathBuf,
+  pub line : usize,
+}
+
+impl fmt::Display for ApiProblem {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    write!(f,"{} {} in {}:{}",Red.paint(self.function.clone()),self.message,Yellow.paint(self.file.display().to_string()),self.line)
+  }
+}
+
+pub fn check_library(path : &Path, definition : &LibraryDefinition) {
+  //! warns about every `love.*` use that won't work with the LÖVE version the library requires.
+  let love : String = match definition.love {
+    None => { output_debug!("No LÖVE version required by {}, skipping the LÖVE api check.",Blue.paint(definition.name.clone())); return; }
+    Some(ref love) => { love.to_string() }
+  };
+
+  if let Some(references) = definition.references() {
+    let mut files : Vec<String> = references.values().cloned().collect();
+    files.sort();
+    files.dedup();
+
+    for file in files {
+      let src_path = require_path(path,&file);
+      let contents : String = library::luafile::get_raw_file_contents(&src_path);
+      for problem in find_problems(&contents,&src_path,&love) { output_warning!("{}",problem.to_string()); }
+    }
+  }
+}
+
+pub fn find_problems(contents : &str, src_path : &Path, love : &str) -> Vec<ApiProblem> {
+  //! a change is a problem if any of the versions the requirement allows has it, so `>=0.9` still
+  //! warns about the functions removed in 11.0.
+  let required : Range = requirement_range(love);
+  let tokens : Vec<Token> = match lexer::tokenize(contents) {
+    Ok(tokens) => { tokens.into_iter().filter(|token| token.kind != TokenKind::Comment).collect() }
+    Err(_) => { return Vec::new(); }
+  };
+
+  let mut problems : Vec<ApiProblem> = Vec::new();
+
+  let mut i : usize = 0;
+  while i < tokens.len() {
+    let after_field : bool = i > 0 && (tokens[i - 1].text == "." || tokens[i - 1].text == ":");
+    if tokens[i].kind != TokenKind::Name || tokens[i].text != "love" || after_field { i += 1; continue; }
+
+    // the full name, `love.graphics.setColor`
+    let line : usize = tokens[i].line;
+    let mut name : String = tokens[i].text.clone();
+    i += 1;
+    while i + 1 < tokens.len() && tokens[i].text == "." && tokens[i + 1].kind == TokenKind::Name {
+      name = format!("{}.{}",name,tokens[i + 1].text);
+      i += 2;
+    }
+
+    let arguments : Vec<Vec<&Token>> = if i < tokens.len() && tokens[i].text == "(" { call_arguments(&tokens,i) } else { Vec::new() };
+
+    for change in API_CHANGES.iter() {
+      if name != change.name && !name.starts_with(&format!("{}.",change.name)) { continue; }
+
+      let message : Option<String> = if let Some(removed) = change.removed.filter(|removed| required.reaches(removed)) {
+        Some(format!("was removed in LÖVE {}",removed))
+      } else if let Some(added) = change.added.filter(|added| required.starts_before(added)) {
+        Some(format!("was added in LÖVE {}",added))
+      } else { change.changed.filter(|changed| required.reaches(changed) && change.check.map(|check| check(&arguments)).unwrap_or(false)).map(|changed| format!("changed in LÖVE {}",changed)) };
+
+      if let Some(message) = message {
+        let mut message : String = format!("{} but the library requires LÖVE {}",message,love);
+        if !change.note.is_empty() { message = format!("{} ({})",message,change.note); }
+        problems.push(ApiProblem { function : name.clone(), message, file : src_path.to_path_buf(), line });
+      }
+    }
+  }
+
+  problems
+}
+
+fn call_arguments(tokens : &[Token], open : usize) -> Vec<Vec<&Token>> {
+  //! the tokens of each argument of the call starting at the `(` at `open`.
+  let mut arguments : Vec<Vec<&Token>> = Vec::new();
+  let mut current : Vec<&Token> = Vec::new();
+  let mut depth : usize = 0;
+
+  for token in tokens[open + 1..].iter() {
+    match token.text.as_str() {
+      "(" | "{" | "[" => { depth += 1; }
+      ")" | "}" | "]" => { if depth == 0 { break; } depth -= 1; }
+      "," if depth == 0 => { arguments.push(current); current = Vec::new(); continue; }
+      _ => { }
+    }
+    current.push(token);
+  }
+  if !current.is_empty() { arguments.push(current); }
+  arguments
+}
+
+fn has_one_argument(arguments : &[Vec<&Token>]) -> bool { arguments.len() == 1 }
+
+fn has_color_above_one(arguments : &[Vec<&Token>]) -> bool {
+  //! any plain number bigger than 1 means the colors are still 0 to 255.
+  arguments.iter().any(|argument| argument.len() == 1 && argument[0].kind == TokenKind::Number && argument[0].text.parse::<f64>().map(|value| value > 1.0).unwrap_or(false))
+}
+
+// the versions a requirement allows
+struct Range {
+  lowest : Vec<u32>,
+  // the first version that isn't allowed anymore, `None` if every newer version is
+  below : Option<Vec<u32>>,
+}
+
+impl Range {
+  fn starts_before(&self, version : &str) -> bool {
+    //! if it allows a version older than `version`.
+    compare(&self.lowest,&parse_version(version)) < 0
+  }
+
+  fn reaches(&self, version : &str) -> bool {
+    //! if it allows `version` or a newer one.
+    match self.below { None => { true } Some(ref below) => { compare(below,&parse_version(version)) > 0 } }
+  }
+}
+
+fn requirement_range(requirement : &str) -> Range {
+  //! `^11.0` is everything from 11.0 up to 12.0, `~11.2` up to 11.3, `>=0.9` has no end and a plain version
+  //! is only that version. More than one, like `>=0.10, <11.0`, allows what all of them allow.
+  let mut range : Range = Range { lowest : vec![0], below : None };
+
+  for part in requirement.split(|c : char| c == ',' || c.is_whitespace()).filter(|part| !part.is_empty()) {
+    let operator : String = part.chars().take_while(|c| !c.is_ascii_digit()).collect();
+    let version : Vec<u32> = parse_version(&part[operator.len()..]);
+
+    let (lowest,below) : (Option<Vec<u32>>,Option<Vec<u32>>) = match operator.as_str() {
+      "*" => { (None,None) }
+      "^" => { (Some(version.clone()),Some(bump(&version,version.iter().position(|&number| number > 0).unwrap_or(version.len() - 1)))) }
+      "~" => { (Some(version.clone()),Some(bump(&version,if version.len() > 1 { 1 } else { 0 }))) }
+      ">=" => { (Some(version),None) }
+      ">" => { (Some(just_after(&version)),None) }
+      "<" => { (None,Some(version)) }
+      "<=" => { (None,Some(just_after(&version))) }
+      _ => { (Some(version.clone()),Some(just_after(&version))) }
+    };
+
+    if let Some(lowest) = lowest { if compare(&range.lowest,&lowest) < 0 { range.lowest = lowest; } }
+    if let Some(below) = below {
+      if range.below.as_ref().map(|current| compare(current,&below) > 0).unwrap_or(true) { range.below = Some(below); }
+    }
+  }
+
+  range
+}
+
+fn parse_version(version : &str) -> Vec<u32> {
+  //! the numbers of a version.
+  version.split(".").map(|part| part.chars().filter(|c| c.is_ascii_digit()).collect::<String>().parse::<u32>().unwrap_or(0)).collect()
+}
+
+fn bump(version : &[u32], index : usize) -> Vec<u32> {
+  //! the next version at `index`, `bump(1.2.3,1)` is 1.3.
+  let mut bumped : Vec<u32> = version[..index].to_vec();
+  bumped.push(version[index] + 1);
+  bumped
+}
+
+fn just_after(version : &[u32]) -> Vec<u32> {
+  //! a version newer than `version` but older than anything else, since missing parts count as 0.
+  let mut after : Vec<u32> = version.to_vec();
+  after.push(1);
+  after
+}
+
+fn compare(a : &[u32], b : &[u32]) -> i32 {
+  //! -1 if `a` is older than `b`, 0 if the same and 1 if newer.
+  for i in 0..a.len().max(b.len()) {
+    let a_part : u32 = *a.get(i).unwrap_or(&0);
+    let b_part : u32 = *b.get(i).unwrap_or(&0);
+    if a_part < b_part { return -1; }
+    if a_part > b_part { return 1; }
+  }
+  0
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use super::find_problems;
+
+  fn problems(source : &str, love : &str) -> Vec<String> {
+    find_problems(source,&PathBuf::from("main.lua"),love).into_iter().map(|problem| format!("{}:{} {}",problem.function,problem.line,problem.message)).collect()
+  }
+
+  #[test]
+  fn removed_and_added() {
+    let source : &str = "if love.filesystem.exists('a') then\n  love.graphics.points(1, 2)\nend";
+
+    let found = problems(source,"11.1");
+    assert_eq!(found.len(),1);
+    assert!(found[0].starts_with("love.filesystem.exists:1 was removed in LÖVE 11.0"));
+    assert!(found[0].ends_with("(use love.filesystem.getInfo)"));
+
+    let found = problems(source,"0.9.2");
+    assert_eq!(found.len(),1);
+    assert!(found[0].starts_with("love.graphics.points:2 was added in LÖVE 0.10.0"));
+
+    assert!(problems(source,"0.10.2").is_empty());
+  }
+
+  #[test]
+  fn modules_cover_their_functions() {
+    assert_eq!(problems("local r = love.math.random()","0.8.0").len(),1);
+    assert!(problems("local r = love.math.random()","0.9.0").is_empty());
+  }
+
+  #[test]
+  fn changed_calls() {
+    assert_eq!(problems("love.graphics.setColor(255, 0, 0)","11.0").len(),1);
+    assert!(problems("love.graphics.setColor(1, 0, 0.5)","11.0").is_empty());
+    // can't tell from a variable
+    assert!(problems("love.graphics.setColor(r, g, b)","11.0").is_empty());
+    // the old way was right back then
+    assert!(problems("love.graphics.setColor(255, 0, 0)","0.10.2").is_empty());
+
+    assert_eq!(problems("love.audio.newSource('music.ogg')","11.0").len(),1);
+    assert!(problems("love.audio.newSource('music.ogg', 'stream')","11.0").is_empty());
+  }
+
+  #[test]
+  fn requirement_ranges() {
+    assert_eq!(problems("love.graphics.newScreenshot()","^11.0").len(),1);
+    assert_eq!(problems("love.graphics.newScreenshot()","~11.2").len(),1);
+
+    // the range goes past the removal
+    assert_eq!(problems("love.filesystem.exists('a')",">=0.9").len(),1);
+    assert_eq!(problems("love.filesystem.exists('a')","^0.10").len(),0);
+    assert_eq!(problems("love.filesystem.exists('a')",">=0.9, <11.0").len(),0);
+    assert_eq!(problems("love.filesystem.exists('a')","<=11.0").len(),1);
+
+    // or starts before it was added
+    assert_eq!(problems("love.graphics.points(1, 2)",">=0.10.0").len(),0);
+    assert_eq!(problems("love.graphics.points(1, 2)","~0.10.2").len(),0);
+    assert_eq!(problems("love.graphics.points(1, 2)","<0.10").len(),1);
+    assert_eq!(problems("love.graphics.points(1, 2)","*").len(),1);
+  }
+
+  #[test]
+  fn only_code() {
+    assert!(problems("-- love.filesystem.exists\nlocal s = 'love.filesystem.exists'\nlocal t = self.love.filesystem.exists","11.0").is_empty());
+  }
+}
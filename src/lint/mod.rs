@@ -0,0 +1,4 @@
+pub mod dialect;
+pub mod globals;
+pub mod love;
+pub mod unused;
@@ -0,0 +1,194 @@
+//! finds lua files in the library that never make it into the compiled library.
+
+use ansi_term::Colour::Yellow;
+use regex;
+
+use std::path::{Path,PathBuf};
+use std::fmt;
+
+use library;
+use library::lualibdef::LibraryDefinition;
+use processing::compile::require_path;
+use processing::lexer::{self,TokenKind};
+
+pub struct UnusedFile {
+  pub file : PathBuf,
+}
+
+impl fmt::Display for UnusedFile {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    write!(f,"{} is not included in the library, add it to [requires] or [internal], or to ignore",Yellow.paint(self.file.display().to_string()))
+  }
+}
+
+pub fn warn_unused(path : &PathBuf, dest : &PathBuf, definition : &LibraryDefinition) {
+  for unused in find_unused(path,dest,definition) { output_warning!("{}",unused.to_string()); }
+}
+
+pub fn find_unused(path : &PathBuf, dest : &PathBuf, definition : &LibraryDefinition) -> Vec<UnusedFile> {
+  //! all the `.lua` files under the library root that aren't a require or internal module.
+  //!
+  //! hidden folders, the compile folder and anything matching the `ignore` list are skipped. Files that an included
+  //! file loads with a normal `require` count as included too, and so do the ones they require.
+
+  let mut included : Vec<PathBuf> = Vec::new();
+  if let Some(references) = definition.references() {
+    for (_,file) in references { included.push(require_path(path,&file)); }
+  }
+  // files only used by a feature that isn't on still belong to the library
+  for file in definition.feature_files() { included.push(require_path(path,&file)); }
+
+  // follows the plain requires, each file is only looked at once
+  let mut index : usize = 0;
+  while index < included.len() {
+    for file in required_files(&included[index],path) {
+      if !included.contains(&file) { included.push(file); }
+    }
+    index += 1;
+  }
+
+  let ignore : Vec<regex::Regex> = match definition.ignore {
+    None => { Vec::new() }
+    Some(ref patterns) => { patterns.iter().filter_map(|pattern| pattern_to_regex(pattern)).collect() }
+  };
+
+  let mut files : Vec<PathBuf> = Vec::new();
+  find_lua_files(path,path,dest,&ignore,&mut files);
+  files.sort();
+
+  let mut unused : Vec<UnusedFile> = Vec::new();
+  for file in files {
+    if !included.contains(&file) { unused.push(UnusedFile { file }); }
+  }
+  unused
+}
+
+fn find_lua_files(root : &PathBuf, folder : &Path, dest : &PathBuf, ignore : &Vec<regex::Regex>, files : &mut Vec<PathBuf>) {
+  if let Ok(entries) = folder.read_dir() {
+    for entry in entries.flatten() {
+      let entry_path : PathBuf = entry.path();
+      let name : String = entry.file_name().to_string_lossy().to_string();
+      if name.starts_with(".") || &entry_path == dest { continue; }
+
+      // the path inside the library, always with `/` so the patterns work everywhere
+      let relative : String = match entry_path.strip_prefix(root) {
+        Err(_) => { continue; }
+        Ok(relative) => { relative.components().map(|part| part.as_os_str().to_string_lossy().to_string()).collect::<Vec<String>>().join("/") }
+      };
+      if ignore.iter().any(|pattern| pattern.is_match(&relative)) { continue; }
+
+      if entry_path.is_dir() { find_lua_files(root,&entry_path,dest,ignore,files); }
+      else if name.ends_with(".lua") { files.push(entry_path); }
+    }
+  }
+}
+
+fn pattern_to_regex(pattern : &str) -> Option<regex::Regex> {
+  //! turns an ignore pattern into a regex, `*` is anything but `/`, `**` is anything.
+  //! a pattern matching a folder matches everything inside of it.
+  let mut expression : String = String::from("^");
+  let mut chars = pattern.trim_matches('/').chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '*' => {
+        if chars.peek() == Some(&'*') { chars.next(); expression.push_str(".*"); }
+        else { expression.push_str("[^/]*"); }
+      }
+      '?' => { expression.push_str("[^/]"); }
+      c => { expression.push_str(&regex::escape(&c.to_string())); }
+    }
+  }
+  expression.push_str("(/.*)?$");
+
+  match regex::Regex::new(&expression) {
+    Ok(re) => { Some(re) }
+    Err(error) => { output_warning!("Ignoring bad ignore pattern {}: {}",Yellow.paint(pattern.to_string()),error.to_string()); None }
+  }
+}
+
+fn required_files(source : &PathBuf, root : &Path) -> Vec<PathBuf> {
+  //! the files inside the library that `source` loads with a `require "..."`.
+  let mut files : Vec<PathBuf> = Vec::new();
+  if !source.exists() { return files; }
+
+  let contents : String = library::luafile::get_raw_file_contents(source);
+  let tokens = match lexer::tokenize(&contents) { Ok(tokens) => { tokens } Err(_) => { return files; } };
+
+  for (index,token) in tokens.iter().enumerate() {
+    if token.kind != TokenKind::Name || token.text != "require" { continue; }
+
+    // `require "name"` or `require("name")`
+    let argument = match tokens.get(index + 1) {
+      Some(next) if next.kind == TokenKind::String => { Some(next) }
+      Some(next) if next.text == "(" => { tokens.get(index + 2).filter(|argument| argument.kind == TokenKind::String) }
+      _ => { None }
+    };
+
+    if let Some(argument) = argument {
+      let name : &str = argument.text.trim_matches(|c| c == '"' || c == '\'');
+      let file : PathBuf = require_path(root,name);
+      if file.exists() { files.push(file); }
+    }
+  }
+  files
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env;
+  use std::fs;
+  use std::path::{Path,PathBuf};
+  use std::process;
+
+  use library::luafile;
+  use super::{find_unused,pattern_to_regex};
+
+  fn matches(pattern : &str, path : &str) -> bool { pattern_to_regex(pattern).unwrap().is_match(path) }
+
+  fn write(path : &Path, contents : &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path,contents).unwrap();
+  }
+
+  #[test]
+  fn patterns() {
+    // a folder matches everything inside of it
+    assert!(matches("spec","spec"));
+    assert!(matches("/spec/","spec/deep/a_spec.lua"));
+    assert!(!matches("spec","specs/a.lua"));
+    assert!(!matches("spec","src/spec"));
+
+    // `*` stays in one folder, `**` doesn't
+    assert!(matches("examples/*.lua","examples/a.lua"));
+    assert!(!matches("examples/*.lua","examples/more/a.lua"));
+    assert!(matches("**/test_*.lua","src/deep/test_shapes.lua"));
+    assert!(matches("src/?.lua","src/a.lua"));
+    assert!(!matches("src/?.lua","src/ab.lua"));
+
+    // everything else is literal
+    assert!(matches("a.lua","a.lua"));
+    assert!(!matches("a.lua","axlua"));
+  }
+
+  #[test]
+  fn unused_files() {
+    let root : PathBuf = env::temp_dir().join(format!("lmake-unused-{}",process::id()));
+    let _ = fs::remove_dir_all(&root);
+    write(&root.join("src/shapes.lua"),"local helper = require \"src.helper\"\nreturn {}");
+    write(&root.join("src/helper.lua"),"return require('src.deeper')");
+    write(&root.join("src/deeper.lua"),"return {}");
+    write(&root.join("src/extra.lua"),"return {}");
+    write(&root.join("src/stray.lua"),"return {}");
+    write(&root.join("spec/shapes_spec.lua"),"return {}");
+    write(&root.join(".git/hooks.lua"),"return {}");
+    write(&root.join("bin/shapes.lua"),"return {}");
+
+    let definition = luafile::parse_lualib_settings("name = 'shapes'\nuser = 'someone'\nauthor = 'someone'\nversion = '1.0.0'\n\
+      ignore = ['spec']\n[requires]\n_ = 'src.shapes'\n[features.extra]\nrequires = { extra = 'src.extra' }").unwrap();
+
+    let unused : Vec<PathBuf> = find_unused(&root,&root.join("bin"),&definition).into_iter().map(|unused| unused.file).collect();
+    assert_eq!(unused,vec![root.join("src/stray.lua")]);
+
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
@@ -0,0 +1,185 @@
+use ansi_term::Colour::{Red,Yellow,Green,Blue};
+
+use std::path::{Path,PathBuf};
+use std::collections::HashMap;
+
+use processing::compile::{require_path,get_library_latest_version,get_tag_names,get_definition_at_tag};
+use library::luafile::get_lualib_settings;
+use library::lualibdef::{LibraryDefinition,Profile};
+use love::project::project;
+use version::version::Version;
+
+pub fn describe(path : &Path, definition : &LibraryDefinition) -> String {
+  //! builds the full description of a library, used by `lmake info`.
+  //!
+  //! shows every field from the definition, where each require is loaded from, the resolved
+  //! dependency versions and the available tags, checking each tag against the LÖVE version
+  //! of the project in the current folder (if there is one).
+
+  let mut text : String = format!("{}/{} {}",&definition.user,Blue.paint(definition.name.clone()),Yellow.paint(definition.version.to_string()));
+  text = format!("{}\n  path: {}",text,path.display());
+  text = format!("{}\n  author: {}",text,&definition.author);
+  if let Some(ref email) = definition.email { text = format!("{}\n  email: {}",text,email); }
+  if let Some(ref description) = definition.description { text = format!("{}\n  description: {}",text,description); }
+  if let Some(ref authors) = definition.authors { text = format!("{}\n  authors: {}",text,authors.join(", ")); }
+  if let Some(ref license) = definition.license { text = format!("{}\n  license: {}",text,license); }
+  if let Some(ref homepage) = definition.homepage { text = format!("{}\n  homepage: {}",text,homepage); }
+  if let Some(ref keywords) = definition.keywords { text = format!("{}\n  keywords: {}",text,keywords.join(", ")); }
+  if let Some(ref love) = definition.love { text = format!("{}\n  love: {}",text,love); }
+  if let Some(ref upstream) = definition.upstream { text = format!("{}\n  upstream: {}",text,upstream); }
+  if let Some(ref target_lua) = definition.target_lua { text = format!("{}\n  target-lua: {}",text,target_lua); }
+  if let Some(ref header) = definition.header { text = format!("{}\n  header: {}",text,header); }
+  if let Some(ref test) = definition.test { text = format!("{}\n  test: {}",text,test); }
+  if let Some(ref ignore) = definition.ignore { text = format!("{}\n  ignore: {}",text,ignore.join(", ")); }
+  if let Some(ref globals) = definition.globals { text = format!("{}\n  globals: {}",text,globals.join(", ")); }
+
+  if let Some(ref options) = definition.options {
+    let mut keys : Vec<&String> = options.keys().collect();
+    keys.sort();
+    text = format!("{}\n  options:",text);
+    for key in keys { text = format!("{}\n    {} = {:?}",text,key,options[key]); }
+  }
+
+  if let Some(ref defines) = definition.defines {
+    let mut keys : Vec<&String> = defines.keys().collect();
+    keys.sort();
+    text = format!("{}\n  defines:",text);
+    for key in keys { text = format!("{}\n    {} = {}",text,key,defines[key]); }
+  }
+
+  if let Some(ref profiles) = definition.profile {
+    let mut names : Vec<&String> = profiles.keys().collect();
+    names.sort();
+    text = format!("{}\n  profiles:",text);
+    for name in names { text = format!("{}\n    {}: {}",text,name,profile_summary(&profiles[name])); }
+  }
+
+  if let Some(ref targets) = definition.target {
+    text = format!("{}\n  targets:",text);
+    for target in targets.iter() {
+      let mut parts : Vec<String> = Vec::new();
+      if let Some(ref compiled_name) = target.compiled_name { parts.push(format!("compiled-name {}",compiled_name)); }
+      if let Some(ref requires) = target.requires { parts.push(format!("requires {}",requires.join(", "))); }
+      if let Some(ref profile) = target.profile { parts.push(format!("profile {}",profile)); }
+      if let Some(ref options) = target.options { parts.push(format!("options {}",sorted_keys(options).join(", "))); }
+      text = format!("{}\n    {}: {}",text,Blue.paint(target.name.clone()),parts.join("; "));
+    }
+  }
+
+  if let Some(ref features) = definition.features {
+    let mut names : Vec<&String> = features.keys().collect();
+    names.sort();
+    let defaults : Vec<String> = definition.default_features.clone().unwrap_or_default();
+    text = format!("{}\n  features:",text);
+    for name in names {
+      let feature = &features[name];
+      let mut parts : Vec<String> = Vec::new();
+      if let Some(ref requires) = feature.requires { parts.push(format!("requires {}",sorted_keys(requires).join(", "))); }
+      if let Some(ref internal) = feature.internal { parts.push(format!("internal {}",sorted_keys(internal).join(", "))); }
+      if let Some(ref dependencies) = feature.dependencies { parts.push(format!("dependencies {}",sorted_keys(dependencies).join(", "))); }
+      if let Some(ref enables) = feature.features { parts.push(format!("features {}",enables.join(", "))); }
+      text = format!("{}\n    {}{}: {}",text,name,if defaults.contains(name) { " (default)" } else { "" },parts.join("; "));
+    }
+  }
+
+  // requires and internal modules, with the files they point to
+  if let Some(ref requires) = definition.requires { text = files_section(&text,"requires",path,requires); }
+  if let Some(ref internal) = definition.internal { text = files_section(&text,"internal",path,internal); }
+
+  // dependencies, with the version they resolve to
+  if let Some(ref dependencies) = definition.dependencies {
+    let mut names : Vec<&String> = dependencies.keys().collect();
+    names.sort();
+    text = format!("{}\n  dependencies:",text);
+    for name in names {
+      let library_name : String = dependencies[name].library_name(name);
+      let requested : String = dependencies[name].version.clone().unwrap_or("*".to_string());
+      // a `path` or a workspace member is used straight from its folder, whatever its version is
+      let resolved : String = match definition.dependency_source(&library_name,&dependencies[name]) {
+        Some(source) => {
+          match get_lualib_settings(&source) {
+            None => { format!("{} ({})",Red.paint("not found"),source.display()) }
+            Some(source_definition) => { format!("{} ({})",Yellow.paint(source_definition.version.to_string()),source.display()) }
+          }
+        }
+        None => {
+          match Version::from_str(&requested) {
+            None => { format!("{}",Red.paint("malformed version")) }
+            Some(version) => {
+              match get_library_latest_version(&library_name,&version) {
+                None => { format!("{}",Red.paint("not found")) }
+                Some(latest) => { format!("{}",Yellow.paint(latest.to_string())) }
+              }
+            }
+          }
+        }
+      };
+      text = format!("{}\n    {} = {} {} -> {}",text,name,library_name,requested,resolved);
+    }
+  }
+
+  // tags, checking if they work with the current project
+  let project_version : Option<Version> = project::get_required_version(&PathBuf::from(".")).ok();
+  let tags = get_tag_names(path);
+  if !tags.is_empty() {
+    text = format!("{}\n  tags:",text);
+    for tag in tags.iter() {
+      let compatibility : String = match project_version {
+        None => { String::new() }
+        Some(ref project_version) => {
+          match get_definition_at_tag(path,tag) {
+            None => { format!(" ({})",Red.paint("no definition")) }
+            Some(tag_definition) => {
+              match tag_definition.love {
+                None => { format!(" ({})",Yellow.paint("no love requirement")) }
+                Some(ref love) => {
+                  if project_version.is_compatible_with(love) { format!(" ({} {})",Green.paint("compatible with LOVE"),project_version) }
+                  else { format!(" ({} {}, requires {})",Red.paint("not compatible with LOVE"),project_version,love) }
+                }
+              }
+            }
+          }
+        }
+      };
+      text = format!("{}\n    {}{}",text,tag,compatibility);
+    }
+  }
+
+  text
+}
+
+fn profile_summary(profile : &Profile) -> String {
+  //! the options a profile sets, in the order they are written in the README.
+  let mut parts : Vec<String> = Vec::new();
+  if let Some(ref inherits) = profile.inherits { parts.push(format!("inherits {}",inherits)); }
+  if let Some(remove_comments) = profile.remove_comments { parts.push(format!("remove-comments = {}",remove_comments)); }
+  if let Some(minify) = profile.minify { parts.push(format!("minify = {}",minify)); }
+  if let Some(debug_build) = profile.debug_build { parts.push(format!("debug-build = {}",debug_build)); }
+  if let Some(ref defines) = profile.defines { parts.push(format!("defines {}",sorted_keys(defines).join(", "))); }
+  if let Some(ref output) = profile.output { parts.push(format!("output = {}",output)); }
+  if let Some(name_with_version) = profile.name_with_version { parts.push(format!("name-with-version = {}",name_with_version)); }
+  if let Some(ref compiled_name) = profile.compiled_name { parts.push(format!("compiled-name = {}",compiled_name)); }
+  parts.join("; ")
+}
+
+fn sorted_keys<T>(map : &HashMap<String,T>) -> Vec<String> {
+  let mut keys : Vec<String> = map.keys().cloned().collect();
+  keys.sort();
+  keys
+}
+
+fn files_section(text : &str, title : &str, path : &Path, files : &HashMap<String,String>) -> String {
+  let mut keys : Vec<&String> = files.keys().collect();
+  keys.sort();
+
+  let mut text : String = format!("{}\n  {}:",text,title);
+  for key in keys {
+    let file_path = require_path(path,&files[key]);
+    let size : String = match file_path.metadata() {
+      Err(_) => { format!("{}",Red.paint("missing")) }
+      Ok(metadata) => { format!("{} bytes",metadata.len()) }
+    };
+    text = format!("{}\n    {} = {} ({}, {})",text,key,&files[key],file_path.display(),size);
+  }
+  text
+}
@@ -1,15 +1,26 @@
-use std::path::PathBuf;
+use std::fs;
+use std::io::Write;
+use std::path::{Path,PathBuf};
+use std::sync::Arc;
 
 use ansi_term::Colour::{Red,Yellow,Blue};
 
-use processing::compile::{get_library_path,get_library_latest_version,get_library_path_git};
+use library;
+use library::lualibdef::{FeatureRequest,Profile};
+use processing;
+use processing::settings::Settings;
+use processing::notices::{self,Notice};
+use processing::compile::{get_library_path,get_library_latest_version,get_library_path_git,ResolvedDependency};
 
 use lpsettings;
 use love::project::project;
 use version::version::Version;
 
-pub fn from_toml(des : &PathBuf) {
-  //! goes through the toml file and compiles all the project libraries
+pub fn from_toml(des : &Path, profile_name : &str, job_count : Option<usize>) {
+  //! goes through the toml file and compiles all the project libraries with the profile called `profile_name`,
+  //! `job_count` at the same time
+  //!
+  //! the libraries are compiled on the thread pool, the results are printed in name order once everything is done.
 
   match lpsettings::get_raw_local(Some("project.libraries")) {
     None => { output_error!("No libraries defined in the local lovepack.toml."); }
@@ -18,47 +29,130 @@ pub fn from_toml(des : &PathBuf) {
       else {
 
         // gets the project version
-        let project_version : Option<Version> = if let Ok(version) = project::get_required_version(&PathBuf::from(".")) { Some(version) } else { None };
+        let project_version : Arc<Option<Version>> = Arc::new(project::get_required_version(&PathBuf::from(".")).ok());
 
         if let Some(hash) = subsetting.to_hash() {
-          for (name,val) in hash.iter() { 
-            
+
+          let mut names : Vec<&String> = hash.keys().collect();
+          names.sort();
+
+          let mut jobs = Vec::new();
+          for name in names.iter() {
+            let val = &hash[*name];
+
             // gets the version, checks for a simple or complex subsetting
-            let version : Option<String> = if val.is_string() { val.to_string() } else { 
+            let version : Option<String> = if val.is_string() { val.to_string() } else {
               match val.to_hash().unwrap().get("version") {
                 None => { None },
                 Some(subsetting) => { subsetting.to_string() }
             }};
 
-            match version {
-              None => { output_error!("No version defined for {}",Red.paint(name.to_string())); }
-              Some(version) => { 
-                // compiles this
-                if version == "local" { 
-                  match get_library_path_git(&name) {
-                    None => { output_error!("Cannot find library {}.",Red.paint(name.to_string())); }
-                    Some(library_path) => {
-                      output_println!("Compiling library: {} ({})",Blue.paint(name.to_string()),Yellow.paint("git".to_string()));
-                      super::super::compile(&library_path,&des,false,&project_version);
-                    }
-                  }
-                }
-                else {
-                  match Version::from_str(&version) {
-                    None => { output_error!("Failed to parse version, is this valid? {}",Red.paint(version.to_string())); },
-                    Some(version) => { 
-                      match get_library_latest_version(&name,&version) {
-                        None => { output_error!("Cannot find library {} version {}.",Red.paint(name.to_string()),Yellow.paint(version.to_string())); }
-                        Some(latest) => { 
-                          match get_library_path(&name,&latest) {
-                            None => { output_error!("Cannot compile {} ({}), library not found.",Red.paint(name.to_string()),Yellow.paint(version.to_string())); }
-                            Some(library_path) => {
-                              output_println!("Compiling library: {} ({})",Blue.paint(name.to_string()),Yellow.paint(latest.to_string()));
-                              super::super::compile(&library_path,&des,false,&project_version);
-                            }
-                          }
-                        }
-                      }
+            // and the features, the same as a dependency, `features = ["a","b"]` and `default-features = false`
+            let features : FeatureRequest = if val.is_string() { FeatureRequest::default() } else {
+              let settings = val.to_hash().unwrap();
+              FeatureRequest {
+                features : settings.get("features").and_then(|features| features.to_array()).unwrap_or(Vec::new()).iter().filter_map(|feature| feature.to_string()).collect(),
+                no_default_features : settings.get("default-features").and_then(|default| default.to_string()).map(|default| default == "false").unwrap_or(false),
+              }
+            };
+
+            let name : String = name.to_string();
+            let des : PathBuf = des.to_path_buf();
+            let project_version = project_version.clone();
+            let profile_name : String = profile_name.to_string();
+
+            jobs.push((name.clone(),move || install_library(&name,&version,&features,&profile_name,&des,&project_version)));
+          }
+
+          let mut installed : Vec<(PathBuf,Vec<ResolvedDependency>)> = Vec::new();
+          for result in processing::jobs::run(jobs,job_count) {
+            match result {
+              // already reported
+              None => { }
+              Some(Err(error)) => { output_error!("{}",error); }
+              Some(Ok((message,library_path,dependencies))) => { 
+                output_println!("{}",message);
+                installed.push((library_path,dependencies));
+              }
+            }
+          }
+
+          write_notices(des,&installed);
+        }
+      }
+    }
+  }
+
+}
+
+fn write_notices(des : &Path, installed : &[(PathBuf,Vec<ResolvedDependency>)]) {
+  //! writes the licenses of every installed library and their dependencies to `THIRD_PARTY_NOTICES`.
+  let mut all : Vec<Notice> = Vec::new();
+  for (library_path,dependencies) in installed.iter() {
+    if let Some(definition) = library::luafile::get_lualib_settings(library_path) {
+      let mut found : Vec<Notice> = vec![notices::from_library(library_path,&definition)];
+      found.extend(notices::dependencies(dependencies));
+      for notice in found {
+        if !all.iter().any(|existing| existing.name == notice.name && existing.version == notice.version) { all.push(notice); }
+      }
+    }
+  }
+  if all.is_empty() { return; }
+  all.sort_by(|a,b| (&a.name,&a.version).cmp(&(&b.name,&b.version)));
+
+  let mut notices_path : PathBuf = des.to_path_buf();
+  notices_path.push("THIRD_PARTY_NOTICES");
+  match fs::File::create(&notices_path) {
+    Err(error) => { output_error!("Could not create \'{}\': {}",Red.paint(notices_path.display().to_string()),Yellow.paint(error.to_string())); }
+    Ok(mut file) => {
+      if let Err(error) = file.write_all(notices::to_file(&all).as_bytes()) { output_error!("Could not write \'{}\': {}",Red.paint(notices_path.display().to_string()),Yellow.paint(error.to_string())); }
+    }
+  }
+}
+
+fn settings(library_path : &Path, name : &str, profile_name : &str) -> Settings {
+  //! the library's own profile, a library doesn't have to have the profile so it gets built like `dev` then.
+  match Settings::load(library_path,profile_name,Profile::default()) {
+    Ok(settings) => { settings }
+    Err(error) => {
+      output_warning!("Building {} without a profile: {}",Blue.paint(name.to_string()),Yellow.paint(error));
+      Settings::new(profile_name,Profile::default(),Profile::default())
+    }
+  }
+}
+
+fn install_library(name : &str, version : &Option<String>, features : &FeatureRequest, profile_name : &str, des : &PathBuf, project_version : &Option<Version>) -> Result<(String,PathBuf,Vec<ResolvedDependency>),String> {
+  //! finds and compiles a single library, returns the message to display, where the library is and the
+  //! dependencies that were compiled into it.
+
+  match *version {
+    None => { Err(format!("No version defined for {}",Red.paint(name.to_string()))) }
+    Some(ref version) => {
+      // compiles this
+      if version == "local" {
+        match get_library_path_git(name) {
+          None => { Err(format!("Cannot find library {}.",Red.paint(name.to_string()))) }
+          Some(library_path) => {
+            match super::super::compile_with_parents(&library_path,des,false,project_version,features,&None,&settings(&library_path,name,profile_name),&Vec::new()) {
+              Err(error) => { Err(format!("Error compiling library {} ({}): {}",Red.paint(name.to_string()),Yellow.paint("git".to_string()),error)) }
+              Ok((_,dependencies)) => { Ok((format!("Compiling library: {} ({})",Blue.paint(name.to_string()),Yellow.paint("git".to_string())),library_path.clone(),dependencies)) }
+            }
+          }
+        }
+      }
+      else {
+        match Version::from_str(version) {
+          None => { Err(format!("Failed to parse version, is this valid? {}",Red.paint(version.to_string()))) },
+          Some(version) => {
+            match get_library_latest_version(name,&version) {
+              None => { Err(format!("Cannot find library {} version {}.",Red.paint(name.to_string()),Yellow.paint(version.to_string()))) }
+              Some(latest) => {
+                match get_library_path(name,&latest) {
+                  None => { Err(format!("Cannot compile {} ({}), library not found.",Red.paint(name.to_string()),Yellow.paint(version.to_string()))) }
+                  Some(library_path) => {
+                    match super::super::compile_with_parents(&library_path,des,false,project_version,features,&None,&settings(&library_path,name,profile_name),&Vec::new()) {
+                      Err(error) => { Err(format!("Error compiling library {} ({}): {}",Red.paint(name.to_string()),Yellow.paint(latest.to_string()),error)) }
+                      Ok((_,dependencies)) => { Ok((format!("Compiling library: {} ({})",Blue.paint(name.to_string()),Yellow.paint(latest.to_string())),library_path.clone(),dependencies)) }
                     }
                   }
                 }
@@ -69,5 +163,4 @@ pub fn from_toml(des : &PathBuf) {
       }
     }
   }
-
-}
\ No newline at end of file
+}
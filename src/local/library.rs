@@ -1,12 +1,15 @@
-use ansi_term::Colour::{Red,Yellow};
+use ansi_term::Colour::{Red,Yellow,Blue};
+use regex::Regex;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path,PathBuf};
+use std::fmt;
 
 use library;
+use processing::compile::get_tag_names;
 use LIBDEFFILE;
 
-pub fn get_local_libraries(root_path : &PathBuf) -> HashMap<String,PathBuf> {
+pub fn get_local_libraries(root_path : &Path) -> HashMap<String,PathBuf> {
   let mut map : HashMap<String,PathBuf> = HashMap::new();
 
   if !root_path.exists() {
@@ -37,4 +40,69 @@ pub fn get_local_libraries(root_path : &PathBuf) -> HashMap<String,PathBuf> {
   }
 
   map
-}
\ No newline at end of file
+}
+
+#[derive(Serialize)]
+pub struct LibraryListing {
+  pub name : String,
+  pub user : String,
+  pub author : String,
+  pub description : Option<String>,
+  pub license : Option<String>,
+  pub homepage : Option<String>,
+  pub keywords : Vec<String>,
+  pub versions : Vec<String>,
+  pub upstream : Option<String>,
+  pub love : Option<String>,
+  pub path : PathBuf,
+}
+
+impl fmt::Display for LibraryListing {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    let mut text : String = format!("{}/{} ({})",&self.user,Blue.paint(self.name.clone()),&self.author);
+    if let Some(ref description) = self.description { text = format!("{}\n    {}",text,description); }
+    text = format!("{}\n    versions: {}",text,Yellow.paint(if !self.versions.is_empty() { self.versions.join(", ") } else { "none".to_string() }));
+    if let Some(ref upstream) = self.upstream { text = format!("{}\n    upstream: {}",text,upstream); }
+    if let Some(ref love) = self.love { text = format!("{}\n    love: {}",text,love); }
+    if let Some(ref license) = self.license { text = format!("{}\n    license: {}",text,license); }
+    if let Some(ref homepage) = self.homepage { text = format!("{}\n    homepage: {}",text,homepage); }
+    if !self.keywords.is_empty() { text = format!("{}\n    keywords: {}",text,self.keywords.join(", ")); }
+    write!(f,"{}",text)
+  }
+}
+
+impl LibraryListing {
+  pub fn matches(&self, pattern : &Regex) -> bool {
+    //! checks if the name, user, keywords or description match the search pattern.
+    if pattern.is_match(&self.name) || pattern.is_match(&self.user) { return true; }
+    if self.keywords.iter().any(|keyword| pattern.is_match(keyword)) { return true; }
+    if let Some(ref description) = self.description { return pattern.is_match(description); }
+    false
+  }
+}
+
+pub fn list_local_libraries(root_path : &Path) -> Vec<LibraryListing> {
+  //! loads every library in the local library folder, sorted by user and name.
+  let mut listings : Vec<LibraryListing> = Vec::new();
+
+  for (_,path) in get_local_libraries(root_path) {
+    if let Some(def) = library::luafile::get_lualib_settings(&path) {
+      listings.push(LibraryListing {
+        name : def.name.clone(),
+        user : def.user.clone(),
+        author : def.author.clone(),
+        description : def.description.clone(),
+        license : def.license.clone(),
+        homepage : def.homepage.clone(),
+        keywords : def.keywords.clone().unwrap_or(Vec::new()),
+        versions : get_tag_names(&path),
+        upstream : def.upstream.clone(),
+        love : def.love.as_ref().map(|love| love.to_string()),
+        path : path.clone(),
+      });
+    }
+  }
+
+  listings.sort_by(|a,b| (&a.user,&a.name).cmp(&(&b.user,&b.name)));
+  listings
+}
@@ -1,2 +1,4 @@
 pub mod library;
-pub mod install;
\ No newline at end of file
+pub mod install;
+pub mod info;
+pub mod release;
\ No newline at end of file
@@ -0,0 +1,300 @@
+use ansi_term::Colour::{Red,Yellow,Blue};
+use git2;
+use regex;
+
+use std::path::{Path,PathBuf};
+use std::fs;
+use std::io::prelude::*;
+use std::cmp::Ordering;
+
+use LIBDEFFILE;
+use library;
+use processing::settings::Settings;
+
+pub fn release(path : &PathBuf, dest : &PathBuf, bump : &str) -> Result<String,&'static str> {
+  //! bumps the version in the library definition, commits it and tags the commit with the new version.
+  //!
+  //! `bump` is either `major`, `minor`, `patch` or the new version itself, which has to be newer than the
+  //! current one. Refuses to do anything if the library has uncommitted changes or the tag already exists,
+  //! and puts the old definition file back if the library doesn't compile or its tests fail with the new version.
+  //!
+  //! the library doesn't have to be at the root of the repository, like a member of a workspace.
+
+  let definition = match library::luafile::get_lualib_settings(path) {
+    None => { return Err("Error loading library definition."); }
+    Some(definition) => { definition }
+  };
+
+  let repo = match git2::Repository::discover(path) {
+    Err(error) => {
+      output_error!("Error opening repository {}: {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string()));
+      return Err("Library is not a git repository.");
+    }
+    Ok(repo) => { repo }
+  };
+
+  // where the library is inside the repository
+  let mut def_path = path.clone();
+  def_path.push(LIBDEFFILE);
+  let relative_path : PathBuf = relative_to_workdir(&repo,&def_path)?;
+  let library_folder : PathBuf = relative_path.parent().map(|folder| folder.to_path_buf()).unwrap_or_default();
+
+  if is_dirty(&repo,&library_folder)? {
+    output_error!("Repository {} has uncommitted changes, commit or stash them first.",Red.paint(path.display().to_string()));
+    return Err("Working tree is dirty.");
+  }
+
+  let new_version : String = bump_version(&definition.version.to_string(),bump)?;
+  if tag_names(&repo).contains(&new_version) {
+    output_error!("Tag {} already exists.",Red.paint(new_version.clone()));
+    return Err("Tag already exists.");
+  }
+
+  // rewrites the definition file with the new version
+  let original : String = read_file(&def_path)?;
+  let updated : String = replace_version(&original,&new_version)?;
+  write_file(&def_path,&updated)?;
+
+  // makes sure it all still works, nothing is committed or tagged if it doesn't compile or the tests fail
+  if let Err(error) = super::super::test(path,dest,&Settings::default()) {
+    output_error!("Cannot release {}: {}",Red.paint(new_version.clone()),Yellow.paint(error.to_string()));
+    write_file(&def_path,&original)?;
+    return Err("Release checks failed.");
+  }
+
+  if let Err(error) = commit_and_tag(&repo,&relative_path,&new_version) {
+    output_error!("Error creating release commit: {}",Yellow.paint(error.to_string()));
+    write_file(&def_path,&original)?;
+    return Err("Could not commit release.");
+  }
+
+  output_debug!("Tagged {} as {}",Blue.paint(definition.name.clone()),Yellow.paint(new_version.clone()));
+  Ok(new_version)
+}
+
+fn bump_version(current : &str, bump : &str) -> Result<String,&'static str> {
+  //! gets the new version string, missing parts of the current version count as 0.
+  //!
+  //! bumping a pre-release releases it when it is already the version being bumped to, so `major` makes
+  //! `2.0.0-beta.1` into `2.0.0` but `2.1.0-beta.1` into `3.0.0`.
+
+  let (mut parts,pre) = match parse_version(current) {
+    Some(version) => { version }
+    None => {
+      output_error!("Cannot bump version {}, only numbered versions can be bumped.",Red.paint(current.to_string()));
+      return Err("Invalid current version.");
+    }
+  };
+
+  match bump {
+    "major" => { if pre.is_none() || parts[1] > 0 || parts[2] > 0 { parts[0] += 1; } parts[1] = 0; parts[2] = 0; }
+    "minor" => { if pre.is_none() || parts[2] > 0 { parts[1] += 1; } parts[2] = 0; }
+    "patch" => { if pre.is_none() { parts[2] += 1; } }
+    version => {
+      if parse_version(version).is_none() {
+        output_error!("{} is not a valid version.",Red.paint(version.to_string()));
+        return Err("Invalid version.");
+      }
+      if !is_newer(version,current) {
+        output_error!("{} is not newer than the current version {}.",Red.paint(version.to_string()),Yellow.paint(current.to_string()));
+        return Err("Version isn't newer.");
+      }
+      return Ok(version.to_string());
+    }
+  }
+
+  Ok(format!("{}.{}.{}",parts[0],parts[1],parts[2]))
+}
+
+fn parse_version(version : &str) -> Option<(Vec<u64>,Option<String>)> {
+  //! the numbers and the pre-release of a version, `1.2-beta.1` is `[1,2,0]` and `beta.1`. Build
+  //! metadata after a `+` is ignored.
+  let version : &str = version.split('+').next().unwrap();
+  let mut split = version.splitn(2,'-');
+
+  let mut parts : Vec<u64> = Vec::new();
+  for part in split.next().unwrap().split('.') {
+    match part.parse::<u64>() { Ok(number) => { parts.push(number); } Err(_) => { return None; } }
+  }
+  if parts.len() > 3 { return None; }
+  while parts.len() < 3 { parts.push(0); }
+
+  match split.next() {
+    None => { Some((parts,None)) }
+    Some(pre) => { if pre.is_empty() { None } else { Some((parts,Some(pre.to_string()))) } }
+  }
+}
+
+fn is_newer(version : &str, current : &str) -> bool {
+  //! compares like semver, a pre-release comes before the release with the same numbers.
+  let (new_parts,new_pre) = match parse_version(version) { Some(version) => { version } None => { return false; } };
+  let (current_parts,current_pre) = match parse_version(current) { Some(version) => { version } None => { return true; } };
+
+  if new_parts != current_parts { return new_parts > current_parts; }
+  match (new_pre,current_pre) {
+    (None,None) => { false }
+    (None,Some(_)) => { true }
+    (Some(_),None) => { false }
+    (Some(new_pre),Some(current_pre)) => { compare_pre_release(&new_pre,&current_pre) == Ordering::Greater }
+  }
+}
+
+fn compare_pre_release(a : &str, b : &str) -> Ordering {
+  //! each part is compared by number if both are numbers, otherwise as text, and numbers come first.
+  let a_parts : Vec<&str> = a.split('.').collect();
+  let b_parts : Vec<&str> = b.split('.').collect();
+  for (a_part,b_part) in a_parts.iter().zip(b_parts.iter()) {
+    let order : Ordering = match (a_part.parse::<u64>(),b_part.parse::<u64>()) {
+      (Ok(a_number),Ok(b_number)) => { a_number.cmp(&b_number) }
+      (Ok(_),Err(_)) => { Ordering::Less }
+      (Err(_),Ok(_)) => { Ordering::Greater }
+      (Err(_),Err(_)) => { a_part.cmp(b_part) }
+    };
+    if order != Ordering::Equal { return order; }
+  }
+  a_parts.len().cmp(&b_parts.len())
+}
+
+fn replace_version(contents : &str, new_version : &str) -> Result<String,&'static str> {
+  //! replaces the top level `version = ".."` line, leaving the rest of the file exactly as it was.
+
+  // only looks before the first table so dependency versions are never touched.
+  let table_re = regex::Regex::new(r"(?m)^\s*\[").unwrap();
+  let split : usize = if let Some(mtch) = table_re.find(contents) { mtch.start() } else { contents.len() };
+
+  let version_re = regex::Regex::new(r#"(?m)^(\s*version\s*=\s*)(["'])[^"'\n]*(["'])"#).unwrap();
+  if !version_re.is_match(&contents[..split]) {
+    output_error!("No {} found in {}.",Red.paint("version"),Yellow.paint(LIBDEFFILE));
+    return Err("No version found.");
+  }
+
+  let replaced = version_re.replace(&contents[..split],format!("${{1}}${{2}}{}${{3}}",new_version).as_str());
+  Ok(format!("{}{}",replaced,&contents[split..]))
+}
+
+pub fn relative_to_workdir(repo : &git2::Repository, path : &Path) -> Result<PathBuf,&'static str> {
+  //! the path as git knows it, from the top of the repository.
+  let workdir : PathBuf = match repo.workdir().and_then(|workdir| workdir.canonicalize().ok()) {
+    Some(workdir) => { workdir }
+    None => { output_error!("The repository doesn't have a working directory."); return Err("Bare repository."); }
+  };
+  match path.canonicalize().ok().and_then(|path| path.strip_prefix(&workdir).ok().map(|path| path.to_path_buf())) {
+    Some(relative) => { Ok(relative) }
+    None => {
+      output_error!("{} isn't inside the repository at {}.",Red.paint(path.display().to_string()),Yellow.paint(workdir.display().to_string()));
+      Err("Library isn't in the repository.")
+    }
+  }
+}
+
+fn tag_names(repo : &git2::Repository) -> Vec<String> {
+  match repo.tag_names(None) {
+    Err(_) => { Vec::new() }
+    Ok(tags) => { tags.iter().filter_map(|tag| tag.map(|tag| tag.to_string())).collect() }
+  }
+}
+
+pub fn is_dirty(repo : &git2::Repository, folder : &Path) -> Result<bool,&'static str> {
+  //! only looks at the library's folder, other libraries in the same repository don't matter.
+  let mut options = git2::StatusOptions::new();
+  options.include_untracked(false).include_ignored(false);
+  if folder.components().count() > 0 { options.pathspec(folder); }
+
+  match repo.statuses(Some(&mut options)) {
+    Err(error) => {
+      output_error!("Cannot read repository status: {}",Yellow.paint(error.to_string()));
+      Err("Cannot read repository status.")
+    }
+    Ok(statuses) => { Ok(!statuses.is_empty()) }
+  }
+}
+
+fn commit_and_tag(repo : &git2::Repository, def_path : &Path, version : &str) -> Result<(),git2::Error> {
+  let signature = repo.signature()?;
+
+  let mut index = repo.index()?;
+  index.add_path(def_path)?;
+  index.write()?;
+  let tree = repo.find_tree(index.write_tree()?)?;
+
+  let parent = repo.head()?.peel_to_commit()?;
+  let message : String = format!("Release {}",version);
+  let commit_id = repo.commit(Some("HEAD"),&signature,&signature,&message,&tree,&[&parent])?;
+
+  let commit = repo.find_object(commit_id,None)?;
+  repo.tag(version,&commit,&signature,&message,false)?;
+  Ok(())
+}
+
+fn read_file(path : &PathBuf) -> Result<String,&'static str> {
+  let mut contents : String = String::new();
+  match fs::File::open(path) {
+    Err(error) => { output_error!("Could not open \'{}\': {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string())); Err("Could not read file.") }
+    Ok(mut file) => {
+      match file.read_to_string(&mut contents) {
+        Err(error) => { output_error!("Could not read \'{}\': {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string())); Err("Could not read file.") }
+        Ok(_) => { Ok(contents) }
+      }
+    }
+  }
+}
+
+fn write_file(path : &PathBuf, contents : &str) -> Result<(),&'static str> {
+  match fs::File::create(path) {
+    Err(error) => { output_error!("Could not create \'{}\': {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string())); Err("Could not write file.") }
+    Ok(mut file) => {
+      match file.write_all(contents.as_bytes()) {
+        Err(error) => { output_error!("Could not write \'{}\': {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string())); Err("Could not write file.") }
+        Ok(_) => { Ok(()) }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{bump_version,replace_version};
+
+  fn bumped(current : &str, bump : &str) -> String { bump_version(current,bump).unwrap() }
+
+  #[test]
+  fn bumps_each_part() {
+    assert_eq!(bumped("1.2.3","major"),"2.0.0");
+    assert_eq!(bumped("1.2.3","minor"),"1.3.0");
+    assert_eq!(bumped("1.2.3","patch"),"1.2.4");
+    // missing parts count as 0
+    assert_eq!(bumped("1.2","patch"),"1.2.1");
+  }
+
+  #[test]
+  fn bumps_pre_releases() {
+    // releases the version the pre-release was leading up to
+    assert_eq!(bumped("2.0.0-beta.1","major"),"2.0.0");
+    assert_eq!(bumped("1.3.0-rc.2","minor"),"1.3.0");
+    assert_eq!(bumped("1.3.1-rc.2","patch"),"1.3.1");
+    // or goes past it when it is a smaller change
+    assert_eq!(bumped("2.1.0-beta.1","major"),"3.0.0");
+    assert_eq!(bumped("1.3.1-rc.2","minor"),"1.4.0");
+  }
+
+  #[test]
+  fn explicit_versions_have_to_be_newer() {
+    assert_eq!(bumped("1.2.3","1.2.4"),"1.2.4");
+    assert_eq!(bumped("1.2.3","1.3.0-alpha"),"1.3.0-alpha");
+    assert_eq!(bumped("1.2.3-beta","1.2.3"),"1.2.3");
+    assert_eq!(bumped("1.2.3-beta.2","1.2.3-beta.10"),"1.2.3-beta.10");
+
+    assert!(bump_version("1.2.3","1.2.3").is_err());
+    assert!(bump_version("1.2.3","1.0.9").is_err());
+    assert!(bump_version("1.2.3","1.2.3-rc.1").is_err());
+    assert!(bump_version("1.2.3-beta","1.2.3-alpha").is_err());
+    assert!(bump_version("1.2.3","next").is_err());
+  }
+
+  #[test]
+  fn replaces_only_the_library_version() {
+    let contents : &str = "name = \"lib\"\nversion = '1.0.0' # current\n\n[dependencies]\nother = { version = \"1.0.0\" }\n";
+    assert_eq!(replace_version(contents,"1.1.0").unwrap(),"name = \"lib\"\nversion = '1.1.0' # current\n\n[dependencies]\nother = { version = \"1.0.0\" }\n");
+    assert!(replace_version("name = \"lib\"\n[package]\nversion = \"1.0.0\"\n","1.1.0").is_err());
+  }
+}
@@ -0,0 +1,60 @@
+//! holding back messages while something runs on the thread pool.
+//!
+//! the `output_*` macros are wrapped here, so a job can keep everything it prints and have it shown
+//! once all the jobs are done, in the same order every time. Everything else prints right away like normal.
+
+use std::cell::RefCell;
+
+pub enum Level {
+  Error,
+  Warning,
+  Debug,
+  Println,
+}
+
+pub struct Message {
+  level : Level,
+  text : String,
+}
+
+thread_local! {
+  static HELD : RefCell<Option<Vec<Message>>> = const { RefCell::new(None) };
+}
+
+pub fn hold() {
+  //! starts keeping the messages of this thread.
+  HELD.with(|held| *held.borrow_mut() = Some(Vec::new()));
+}
+
+pub fn release() -> Vec<Message> {
+  //! stops keeping the messages of this thread and returns the ones that were kept.
+  HELD.with(|held| held.borrow_mut().take().unwrap_or(Vec::new()))
+}
+
+pub fn show(messages : Vec<Message>) {
+  for message in messages { print(message.level,message.text); }
+}
+
+pub fn print(level : Level, text : String) {
+  let message : Option<Message> = HELD.with(move |held| {
+    match *held.borrow_mut() {
+      Some(ref mut messages) => { messages.push(Message { level, text }); None }
+      None => { Some(Message { level, text }) }
+    }
+  });
+
+  // the macros from `output`, the wrapped ones are defined below
+  if let Some(message) = message {
+    match message.level {
+      Level::Error => { output_error!("{}",message.text); }
+      Level::Warning => { output_warning!("{}",message.text); }
+      Level::Debug => { output_debug!("{}",message.text); }
+      Level::Println => { output_println!("{}",message.text); }
+    }
+  }
+}
+
+macro_rules! output_error { ($($arg:tt)*) => { ::messages::print(::messages::Level::Error,format!($($arg)*)) } }
+macro_rules! output_warning { ($($arg:tt)*) => { ::messages::print(::messages::Level::Warning,format!($($arg)*)) } }
+macro_rules! output_debug { ($($arg:tt)*) => { ::messages::print(::messages::Level::Debug,format!($($arg)*)) } }
+macro_rules! output_println { ($($arg:tt)*) => { ::messages::print(::messages::Level::Println,format!($($arg)*)) } }
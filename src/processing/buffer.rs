@@ -1,25 +1,27 @@
-use std::path::PathBuf;
-use std::env;
+use std::path::{Path,PathBuf};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 
 use library::multivalue::Multivalue;
+use library::lualibdef::LibraryDefinition;
+use processing;
+use processing::defines::Define;
+use processing::lexer::TokenKind;
 
-use ansi_term::Colour::{Red,Yellow,Green,Blue};
+use ansi_term::Colour::{Red,Yellow,Green};
 use regex;
 use base64;
 
-pub fn inject_comment_header(buffer : &mut String) {
-  *buffer = format!("{}\n\
-    -- built with {} ({}) <{}>\n\
-    -- a tool for compiling lua libraries from multiple source files and dependencies\n\n",
+pub fn inject_comment_header(buffer : &mut String, path : &PathBuf, definition : &LibraryDefinition) {
+  //! goes at the very top, after the comments are removed so the header is always kept.
+  *buffer = format!("{}{}",processing::header::render(path,definition),buffer);
+}
 
-    buffer,
-    env!("CARGO_PKG_NAME"),
-    env!("CARGO_PKG_VERSION"),
-    "https://github.com/snsvrno/lmake-rs"
-  );
+pub fn inject_license_notices(buffer : &mut String, notices : &str) {
+  //! goes at the very top, after the comments are removed so the notices are always kept.
+  if notices.is_empty() { return; }
+  *buffer = format!("{}{}",notices,buffer);
 }
 
 pub fn inject_preloads(buffer : &mut String, array_of_preloads : &Vec<String>) {
@@ -41,10 +43,39 @@ pub fn inject_basefill(buffer : &mut String, preload_hash : &str) {
 }
 
 pub fn remove_comments(buffer : &mut String) {
-  if let Ok(_) = env::var("LMAKE_REMOVE_COMMENTS") {
-    let re = regex::Regex::new(r"--.*").unwrap();
-    *buffer = re.replace_all(buffer,regex::NoExpand("")).into_owned();
+  let re = regex::Regex::new(r"--.*").unwrap();
+  *buffer = re.replace_all(buffer,regex::NoExpand("")).into_owned();
+}
+
+pub fn minify(buffer : &mut String, keep_lines : bool) {
+  //! removes the comments and the extra whitespace, using the lexer so strings are left alone.
+  //!
+  //! new lines are kept, lua can read `a\n(b)()` differently than `a (b)()`. With `keep_lines` every one
+  //! of them is kept, blank lines and the ones in comments too, so the line numbers in errors stay the same.
+
+  let tokens = match processing::lexer::tokenize(buffer) {
+    Ok(tokens) => { tokens }
+    Err(error) => { output_warning!("Cannot minify, line {}: {}",error.line,Yellow.paint(error.message)); return; }
+  };
+
+  // a space next to these can't change what the code means
+  let separator = |text : &str| -> bool { ["(",")","{","}",",",";"].contains(&text) };
+
+  let mut minified : String = String::new();
+  let mut last : usize = 0;
+  let mut previous : &str = "";
+  for token in tokens.iter().filter(|token| token.kind != processing::lexer::TokenKind::Comment) {
+    let gap : &str = &buffer[last..token.start];
+    if keep_lines && gap.contains('\n') { minified.push_str(&"\n".repeat(gap.matches('\n').count())); }
+    else if gap.contains('\n') { minified.push('\n'); }
+    else if !gap.is_empty() && !minified.is_empty() && !separator(previous) && !separator(&token.text) { minified.push(' '); }
+    minified.push_str(&token.text);
+    last = token.end;
+    previous = &token.text;
   }
+  minified.push('\n');
+
+  *buffer = minified;
 }
 
 pub fn remove_blank_lines(buffer : &mut String) {
@@ -52,7 +83,7 @@ pub fn remove_blank_lines(buffer : &mut String) {
   let re = regex::Regex::new(r"^ *$").unwrap();
 
   for line in buffer.lines() {
-    if !re.is_match(&line) {
+    if !re.is_match(line) {
       temp_buffer = format!("{}\n{}",temp_buffer,line);
     }
   }
@@ -60,117 +91,145 @@ pub fn remove_blank_lines(buffer : &mut String) {
 }
 
 
-pub fn process_depends_references(buffer : &mut String,preload_hash : &HashMap<String,String>) {
-
-  let mut used_vars : Vec<String> = Vec::new();
-
-  let re = regex::Regex::new(r"@([^.]*)").unwrap();
-  
-  // figures out the var names for all of these.
-  for mtch in re.find_iter(&buffer) {
-    used_vars.push(mtch.as_str()[1..].to_string());
-  }
+pub fn preprocess(contents : &str, src_path : &Path, defines : &HashMap<String,Define>) -> Result<String,String> {
+  //! removes the code inside `--#if` blocks whose condition isn't true, run on each file before anything else.
+  //!
+  //! the directives are `--#if`, `--#elseif`, `--#else` and `--#endif`. A condition is a define name, `not NAME`,
+  //! `NAME == value` or `NAME ~= value`, joined with `and` / `or`. Removed lines are left blank so the line
+  //! numbers stay the same. Other comments starting with `--#` are left alone.
+
+  // only comments that start a line can be directives, not `--#` inside of a long string
+  let tokens = processing::lexer::tokenize(contents).map_err(|error| format!("{} in {}:{}",error.message,src_path.display(),error.line))?;
+  let comments : Vec<usize> = tokens.iter().filter(|token| token.kind == TokenKind::Comment).map(|token| token.start).collect();
+
+  // for each open block, the line it started on, if its code is kept and if one of its branches was already taken
+  let mut blocks : Vec<(usize,bool,bool)> = Vec::new();
+  let mut output : Vec<&str> = Vec::new();
+  let mut offset : usize = 0;
+
+  for (index,line) in contents.split('\n').enumerate() {
+    let number : usize = index + 1;
+    let location = || format!("{}:{}",src_path.display(),number);
+    let start : usize = offset + line.len() - line.trim_start().len();
+    offset += line.len() + 1;
+    let parent : bool = blocks.iter().all(|&(_,active,_)| active);
+
+    let (directive,condition) : (&str,&str) = match directive(line.trim()) {
+      Some(directive) if comments.contains(&start) => { directive }
+      _ => {
+        output.push(if parent { line } else { "" });
+        continue;
+      }
+    };
 
-  for var in used_vars {
-    if let Some(value) = preload_hash.get(&var) {
-      *buffer = buffer.replace(
-        &format!("@{}",&var),
-        &format!(" require(\"{}\")",&value)
-      );
+    match directive {
+      "if" => {
+        let value : bool = evaluate(condition,defines).map_err(|error| format!("{} in {}",error,location()))?;
+        blocks.push((number,value,value));
+      }
+      "elseif" | "else" => {
+        let taken : bool = match blocks.last() { Some(&(_,_,taken)) => { taken } None => { return Err(format!("--#{} without --#if in {}",directive,location())); } };
+        let value : bool = if directive == "else" { true } else { evaluate(condition,defines).map_err(|error| format!("{} in {}",error,location()))? };
+        if let Some(block) = blocks.last_mut() { *block = (block.0,!taken && value,taken || value); }
+      }
+      _ => { if blocks.pop().is_none() { return Err(format!("--#endif without --#if in {}",location())); } }
     }
+    output.push("");
   }
 
+  if let Some(&(line,_,_)) = blocks.last() { return Err(format!("--#if without --#endif in {}:{}",src_path.display(),line)); }
+
+  Ok(output.join("\n"))
 }
 
-pub fn process_internal_references(buffer : &mut String, requires : &Option<HashMap<String,String>>, preload_hash : &HashMap<String,String>) {
-  //! replaces @references that are refering to an internal file. These are files in the source tree under 'requires' so if a toml looks like
-  //!
-  //! ```toml
-  //! [requires]
-  //! "defaults.place.two" = "src.functions"
-  //! ```
-  //! and `src.functions` has some functions, this part will replace `@defaults.place.two` with the preload for `src.functions`. If you reference
-  //! `@defaults.place.two:megaFunction()` or `@defaults.place.two.otherVar` it will replace to `[PRELOAD]:megaFunction()` and `[PRELOAD].otherVar`
+fn directive(line : &str) -> Option<(&str,&str)> {
+  //! the directive and its condition, if the line is one. Any other `--#` is a normal comment.
+  if !line.starts_with("--#") { return None; }
+  let line : &str = &line[3..];
 
-  let mut used_vars : Vec<String> = Vec::new();
+  for directive in ["if", "elseif", "else", "endif"].iter() {
+    if !line.starts_with(directive) { continue; }
+    let rest : &str = &line[directive.len()..];
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) { return Some((directive,rest.trim())); }
+  }
+  None
+}
 
-  let re = regex::Regex::new(r"@([A-Za-z0-9._]*)").unwrap();
-  
-  // figures out the var names for all of these.
-  for mtch in re.find_iter(&buffer) {
-    used_vars.push(mtch.as_str()[1..].to_string());
-  }
-
-  if let Some(ref requires) = *requires {
-
-    for var in used_vars {
-      let mut keep_on_going : bool = true;
-      let mut seperated_path : Vec<&str> = var.split(".").collect();
-
-      // goes through the path backwards and checks if they are valid. can't tell the difference because @ref.ref.func and @ref.ref.ref so goes though all of them. 
-      while keep_on_going {
-        if let Some(file_path) = requires.get(&seperated_path.join(".")) {
-          if let Some(preload_path) = preload_hash.get(file_path) {
-            output_debug!("replacing internal reference {} to {}",seperated_path.join("."),preload_path);
-            *buffer = buffer.replace(
-              &format!("@{}",&seperated_path.join(".")),
-              &format!("require(\"{}\")",&preload_path)
-            );
-            keep_on_going = false;
-          } 
-          
-        } else { seperated_path.pop(); }
-        if seperated_path.len() <= 0 { keep_on_going = false; }
-      }
+fn evaluate(condition : &str, defines : &HashMap<String,Define>) -> Result<bool,String> {
+  if condition.is_empty() { return Err("Missing condition".to_string()); }
+
+  for any in condition.split(" or ") {
+    let mut all : bool = true;
+    for part in any.split(" and ") {
+      let part : &str = part.trim();
+      let value : bool = if let Some(rest) = part.strip_prefix("not ") { !is_set(rest.trim(),defines) }
+        else if part.contains("==") || part.contains("~=") {
+          let operator : &str = if part.contains("==") { "==" } else { "~=" };
+          let mut sides = part.splitn(2,operator);
+          let name : &str = sides.next().unwrap().trim();
+          let expected : &str = sides.next().unwrap().trim().trim_matches(|c| c == '"' || c == '\'');
+          let actual : String = defines.get(name).map(|define| define.to_raw()).unwrap_or_default();
+          (actual == expected) == (operator == "==")
+        }
+        else { is_set(part,defines) };
+      all = all && value;
     }
-    
+    if all { return Ok(true); }
   }
+  Ok(false)
+}
 
-
+fn is_set(name : &str, defines : &HashMap<String,Define>) -> bool {
+  //! a define counts as set unless it is missing, `false` or `nil`.
+  match defines.get(name) { None | Some(&Define::Boolean(false)) | Some(&Define::Nil) => { false } Some(_) => { true } }
 }
 
-pub fn embed_assets(buffer : &mut String, path : &PathBuf, options : &Option<HashMap<String,Multivalue>>) {
+pub fn embed_assets(buffer : &mut String, path : &Path, options : &Option<HashMap<String,Multivalue>>) {
+  //! replaces the paths of assets with their contents, `embed` is one extension or a list of them.
   if let Some(ref options) = *options {
     if let Some(values) = options.get("embed") {
         
       match *values {
         Multivalue::Switch(_) => { }
-        Multivalue::Array(ref extensions) => { }
-        Multivalue::Text(ref extension) => { 
-
-          if let Ok(re) = regex::Regex::new(&format!("['|\"]([^\n]*)\\.{}[\"|']",extension)){
-            output_debug!("*.{} embedding activated.",Green.paint(extension.to_string()));
-          
-            let mut matches : Vec<(String,String)> = Vec::new();  
-            for mtch in re.find_iter(&buffer) {
-              // removes the first and last characters, the quotations
-              matches.push((
-                  mtch.as_str().to_string(),
-                  mtch.as_str()[1..mtch.as_str().len()-1].to_string()
-              ));
-            }
-
-            for mtch in matches {
-              let mut new_path = path.clone();
-              new_path.push(&mtch.1);
-
-              if let Some(encoded) = validate_asset(&new_path) {
-                *buffer = buffer.replace(
-                  &mtch.0,
-                  &get_asset_helper(&extension,&mtch.1,&encoded)
-                );
-              }
-            }
-          } else { output_error!("Error building regex for embedding, check toml's options.embed"); }
-
+        Multivalue::Array(ref extensions) => { 
+          for extension in extensions.iter() {
+            if let Multivalue::Text(ref extension) = *extension { embed_extension(buffer,path,extension); }
+          }
         }
+        Multivalue::Text(ref extension) => { embed_extension(buffer,path,extension); }
       }
-      
 
     }
   }
 }
 
+fn embed_extension(buffer : &mut String, path : &Path, extension : &str) {
+  if let Ok(re) = regex::Regex::new(&format!("['|\"]([^\n]*)\\.{}[\"|']",extension)){
+    output_debug!("*.{} embedding activated.",Green.paint(extension.to_string()));
+  
+    let mut matches : Vec<(String,String)> = Vec::new();  
+    for mtch in re.find_iter(buffer) {
+      // removes the first and last characters, the quotations
+      matches.push((
+          mtch.as_str().to_string(),
+          mtch.as_str()[1..mtch.as_str().len()-1].to_string()
+      ));
+    }
+
+    for mtch in matches {
+      let mut new_path = path.to_path_buf();
+      new_path.push(&mtch.1);
+
+      if let Some(encoded) = validate_asset(&new_path) {
+        *buffer = buffer.replace(
+          &mtch.0,
+          &get_asset_helper(extension,&mtch.1,&encoded)
+        );
+      }
+    }
+  } else { output_error!("Error building regex for embedding, check toml's options.embed"); }
+}
+
 fn get_asset_helper(extension : &str, path : &str, converted_asset : &str) -> String {
   match extension {
     "png" => { format!("love.filesystem.newFileData(\'{}\',\'{}\','base64')",&converted_asset,&path) }
@@ -185,7 +244,7 @@ fn validate_asset(path : &PathBuf) -> Option<String> {
 
   let mut file_contents : Vec<u8>= Vec::new();
   if path.exists() {
-    match File::open(&path) {
+    match File::open(path) {
       Err(error) => { output_error!("Cannot open file {}: {}",Red.paint(path.display().to_string()),Yellow.paint(error.to_string())); },
       Ok(mut file) => { 
         match file.read_to_end(&mut file_contents){
@@ -200,4 +259,79 @@ fn validate_asset(path : &PathBuf) -> Option<String> {
   }
 
   None
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use std::collections::HashMap;
+
+  use processing::defines::Define;
+  use super::{preprocess,minify};
+
+  fn run(source : &str) -> Result<String,String> {
+    let mut defines : HashMap<String,Define> = HashMap::new();
+    defines.insert("DEBUG".to_string(),Define::Boolean(false));
+    defines.insert("MODE".to_string(),Define::Text("fast".to_string()));
+    preprocess(source,&PathBuf::from("main.lua"),&defines)
+  }
+
+  #[test]
+  fn blocks() {
+    assert_eq!(run("a()\n--#if DEBUG\nb()\n--#elseif MODE == fast\nc()\n--#else\nd()\n--#endif\ne()\n").unwrap(),"a()\n\n\n\nc()\n\n\n\ne()\n");
+    assert_eq!(run("  --#if not DEBUG\n  a()\n  --#endif").unwrap(),"\n  a()\n");
+  }
+
+  #[test]
+  fn other_comments_are_kept() {
+    let source : &str = "--#region setup\n--#ifdef\n--# if DEBUG\n--#!shebang\nx = 1 --#if DEBUG\n";
+    assert_eq!(run(source).unwrap(),source);
+    // still removed inside a block that is off
+    assert_eq!(run("--#if DEBUG\n--#region\n--#endif\n").unwrap(),"\n\n\n");
+  }
+
+  #[test]
+  fn not_inside_long_strings() {
+    let source : &str = "local help = [[\n--#if DEBUG\n]]\n--[==[\n--#endif\n]==]\n";
+    assert_eq!(run(source).unwrap(),source);
+  }
+
+  #[test]
+  fn mistakes() {
+    assert!(run("--#if DEBUG\n").is_err());
+    assert!(run("--#endif\n").is_err());
+    assert!(run("--#else\n").is_err());
+    assert!(run("--#if\n--#endif\n").is_err());
+    assert!(run("s = [[ not closed\n").is_err());
+  }
+
+  fn minified(source : &str) -> String {
+    let mut buffer : String = source.to_string();
+    minify(&mut buffer,false);
+    buffer
+  }
+
+  #[test]
+  fn minify_keeping_lines() {
+    let source : &str = "--[[ header\n  comment ]]\nlocal a = 1\n\n\n-- a comment\nlocal   b = a\nerror('here')\n";
+    let mut buffer : String = source.to_string();
+    minify(&mut buffer,true);
+    assert_eq!(buffer,"\n\nlocal a = 1\n\n\n\nlocal b = a\nerror('here')\n");
+    assert_eq!(buffer.lines().position(|line| line.contains("error")),source.lines().position(|line| line.contains("error")));
+  }
+
+  #[test]
+  fn minify_around_dashes() {
+    // `- -1` can't become `--1`, that would be a comment
+    assert_eq!(minified("local a = 1 - -1\n"),"local a = 1 - -1\n");
+    assert_eq!(minified("local b = a  --  the rest\n"),"local b = a\n");
+    assert_eq!(minified("f(a)--[[ long\ncomment ]]g(b)\n"),"f(a)\ng(b)\n");
+  }
+
+  #[test]
+  fn minify_leaves_strings_alone() {
+    assert_eq!(minified("local s = \"a  --  b\"\n"),"local s = \"a  --  b\"\n");
+    assert_eq!(minified("local t = [==[\n  keep   --[[ this ]]\n]==]   ..   x\n"),"local t = [==[\n  keep   --[[ this ]]\n]==] .. x\n");
+    assert_eq!(minified("print ( 'a' , \"b\" )\n"),"print('a',\"b\")\n");
+  }
+}
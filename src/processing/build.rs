@@ -0,0 +1,164 @@
+//! the `library._build` table, information about how the library was compiled for bug reports.
+//!
+//! only added when the `build-info` option is set in the definition.
+
+use ansi_term::Colour::Yellow;
+use git2;
+
+use std::env;
+use std::path::PathBuf;
+use std::time::{SystemTime,UNIX_EPOCH};
+
+use library::lualibdef::LibraryDefinition;
+use library::multivalue::Multivalue;
+use local::release;
+use processing::compile::ResolvedDependency;
+
+pub fn is_enabled(definition : &LibraryDefinition) -> bool {
+  matches!(definition.options.as_ref().and_then(|options| options.get("build-info")),Some(&Multivalue::Switch(true)))
+}
+
+pub fn inject_build_info(buffer : &mut String, path : &PathBuf, dependencies : &[ResolvedDependency]) {
+  //! adds the `_build` table right before the final `return library`.
+  //!
+  //! the hash is of the compiled library without the `_build` table, so it can be checked by removing
+  //! the table again.
+  let position : usize = match buffer.rfind("return library") {
+    Some(position) => { position }
+    None => { output_error!("Cannot find the end of the library to add the build information."); return; }
+  };
+
+  let mut lines : Vec<String> = Vec::new();
+  lines.push(format!("  lmake = '{}',",env!("CARGO_PKG_VERSION")));
+  lines.push(format!("  timestamp = {},",timestamp()));
+  if let Some((commit,dirty)) = commit(path) {
+    lines.push(format!("  commit = '{}',",commit));
+    lines.push(format!("  dirty = {},",dirty));
+  }
+
+  let mut dependencies : Vec<(&String,&String)> = dependencies.iter().map(|dependency| (&dependency.name,&dependency.version)).collect();
+  dependencies.sort();
+  lines.push(format!("  dependencies = {{{}}},",dependencies.iter().map(|&(name,version)| format!(" ['{}'] = '{}',",name,version)).collect::<Vec<String>>().join("")));
+
+  lines.push(format!("  hash = '{:016x}',",fnv_hash(buffer)));
+
+  let table : String = format!("library._build = {{\n{}\n}}\n",lines.join("\n"));
+  buffer.insert_str(position,&table);
+}
+
+pub fn timestamp() -> u64 {
+  //! `SOURCE_DATE_EPOCH` if it is set, so builds can be reproduced, otherwise the current time.
+  if let Ok(epoch) = env::var("SOURCE_DATE_EPOCH") {
+    match epoch.parse::<u64>() {
+      Ok(epoch) => { return epoch; }
+      Err(_) => { output_warning!("SOURCE_DATE_EPOCH isn't a number: {}",Yellow.paint(epoch)); }
+    }
+  }
+  match SystemTime::now().duration_since(UNIX_EPOCH) { Ok(duration) => { duration.as_secs() } Err(_) => { 0 } }
+}
+
+pub fn commit(path : &PathBuf) -> Option<(String,bool)> {
+  //! the commit the library's repository is at, and if there are changes that aren't committed in the
+  //! library's folder. Other libraries in the same repository, like the rest of a workspace, don't count.
+  let repo = match git2::Repository::discover(path) { Ok(repo) => { repo } Err(_) => { return None; } };
+  let commit : String = match repo.head().ok().and_then(|head| head.target()) { Some(oid) => { oid.to_string() } None => { return None; } };
+
+  let folder : PathBuf = release::relative_to_workdir(&repo,path).ok()?;
+  let dirty : bool = release::is_dirty(&repo,&folder).unwrap_or(false);
+
+  Some((commit,dirty))
+}
+
+fn fnv_hash(text : &str) -> u64 {
+  //! 64 bit FNV-1a.
+  let mut hash : u64 = 0xcbf29ce484222325;
+  for byte in text.as_bytes() {
+    hash ^= *byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  hash
+}
+
+#[cfg(test)]
+mod tests {
+  use git2;
+
+  use std::env;
+  use std::fs;
+  use std::path::{Path,PathBuf};
+  use std::process;
+
+  use library::luafile;
+  use processing::compile::ResolvedDependency;
+  use super::{commit,fnv_hash,inject_build_info,is_enabled};
+
+  fn write(path : &Path, contents : &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path,contents).unwrap();
+  }
+
+  #[test]
+  fn enabled() {
+    let definition = luafile::parse_lualib_settings("name = 'a'\nuser = 'u'\nauthor = 'a'\nversion = '1.0.0'\n[options]\nbuild-info = true").unwrap();
+    assert!(is_enabled(&definition));
+    let definition = luafile::parse_lualib_settings("name = 'a'\nuser = 'u'\nauthor = 'a'\nversion = '1.0.0'").unwrap();
+    assert!(!is_enabled(&definition));
+  }
+
+  #[test]
+  fn hashes() {
+    assert_eq!(fnv_hash(""),0xcbf29ce484222325);
+    assert_eq!(fnv_hash("a"),0xaf63dc4c8601ec8c);
+  }
+
+  #[test]
+  fn build_table() {
+    let dependencies : Vec<ResolvedDependency> = vec![
+      ResolvedDependency { name : "b".to_string(), version : "2.0.0".to_string(), path : PathBuf::new(), dependencies : Vec::new() },
+      ResolvedDependency { name : "a".to_string(), version : "1.0.0".to_string(), path : PathBuf::new(), dependencies : Vec::new() },
+    ];
+    let original : String = "local library = {}\nreturn library\n".to_string();
+    let mut buffer : String = original.clone();
+    inject_build_info(&mut buffer,&PathBuf::from("/"),&dependencies);
+
+    assert!(buffer.starts_with("local library = {}\nlibrary._build = {\n"));
+    assert!(buffer.ends_with("}\nreturn library\n"));
+    assert!(buffer.contains("  dependencies = { ['a'] = '1.0.0', ['b'] = '2.0.0',},\n"));
+    assert!(buffer.contains(&format!("  hash = '{:016x}',\n",fnv_hash(&original))));
+
+    // nothing to add it to
+    let mut buffer : String = "return {}".to_string();
+    inject_build_info(&mut buffer,&PathBuf::from("/"),&dependencies);
+    assert_eq!(buffer,"return {}");
+  }
+
+  #[test]
+  fn dirty_library_folder() {
+    // a repository with the library and something else next to it
+    let root : PathBuf = env::temp_dir().join(format!("lmake-build-{}",process::id()));
+    let _ = fs::remove_dir_all(&root);
+    write(&root.join("library/lib.toml"),"name = 'library'");
+    write(&root.join("other/lib.toml"),"name = 'other'");
+
+    let repo = git2::Repository::init(&root).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("library/lib.toml")).unwrap();
+    index.add_path(Path::new("other/lib.toml")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("lmake","lmake@example.com").unwrap();
+    let head = repo.commit(Some("HEAD"),&signature,&signature,"first",&tree,&[]).unwrap();
+
+    let library : PathBuf = root.join("library");
+    assert_eq!(commit(&library),Some((head.to_string(),false)));
+
+    // changes somewhere else in the repository don't matter
+    write(&root.join("other/lib.toml"),"name = 'changed'");
+    assert_eq!(commit(&library),Some((head.to_string(),false)));
+
+    write(&root.join("library/lib.toml"),"name = 'changed'");
+    assert_eq!(commit(&library),Some((head.to_string(),true)));
+
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
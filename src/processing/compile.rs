@@ -1,18 +1,33 @@
 use ansi_term::Colour::{Red,Yellow,Blue};
 
-use std::path::PathBuf;
-use std::collections::HashMap;
+use std::path::{Path,PathBuf};
+use std::collections::{HashMap,BTreeMap};
+use std::sync::{Arc,Mutex,Weak};
 
 use LIBDEFFILE;
 use processing;
 use local;
 use library;
-use library::lualibdef::LibraryDefinition;
+use library::lualibdef::{LibraryDefinition,Dependency,FeatureRequest};
+use processing::references::{Resolver,UnresolvedReference};
+use processing::settings::Settings;
 use version::version::Version;
 use lpsettings;
 use git2;
 
-pub fn validate_lualib_path(library_root_path : &PathBuf) -> bool {
+// only the locks that are being used are kept, a lock nothing holds anymore is dropped the next time one is taken
+static CACHE_LOCKS : Mutex<BTreeMap<PathBuf,Weak<Mutex<()>>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone)]
+pub struct ResolvedDependency {
+  pub name : String,
+  pub version : String,
+  // the folder the library was compiled from
+  pub path : PathBuf,
+  pub dependencies : Vec<ResolvedDependency>,
+}
+
+pub fn validate_lualib_path(library_root_path : &Path) -> bool {
   //! checks to see if the supplied path has a library inside of it.
   //! the lib.toml file is what defines a library (or whatever is LIBDEFFILE)
   match library_root_path.exists() {
@@ -21,7 +36,7 @@ pub fn validate_lualib_path(library_root_path : &PathBuf) -> bool {
       false
     },
     true => {
-      let mut lib_file : PathBuf = library_root_path.clone();
+      let mut lib_file : PathBuf = library_root_path.to_path_buf();
       lib_file.push(LIBDEFFILE);
       match lib_file.exists() {
         false => {
@@ -34,90 +49,246 @@ pub fn validate_lualib_path(library_root_path : &PathBuf) -> bool {
   }
 }
 
-pub fn requires(path : &PathBuf, definition : &LibraryDefinition,array_of_preloads : &mut Vec<String>,preload_hash : &mut HashMap<String,String>) {
-  match definition.requires {
-    None => { },
-    Some(ref hash) => {
-      // for each requirement in the definition
-      for (_,file) in hash.iter() {
-        // builds the path to the file
-        let mut src_path = path.clone();
-        let temp_vector : Vec<&str> = file.split(".").collect();
-        for cc in 0..temp_vector.len() { 
-          if cc == (temp_vector.len()-1) { 
-            src_path.push(format!("{}.{}",temp_vector[cc],"lua"));
-          } else {
-            src_path.push(temp_vector[cc]);
-          }
-        }
+pub fn validate_lualib_path_quiet(library_root_path : &Path) -> bool {
+  //! same as `validate_lualib_path` but doesn't complain, for when the path is only one option.
+  let mut lib_file : PathBuf = library_root_path.to_path_buf();
+  lib_file.push(LIBDEFFILE);
+  lib_file.exists()
+}
 
-        let preload_text :String = processing::gen::create_random_preload_name(&definition.name);
-        preload_hash.insert(file.clone(),preload_text.clone());
+pub fn requires(path : &Path, definition : &LibraryDefinition, settings : &Settings, array_of_preloads : &mut Vec<String>,preload_hash : &mut HashMap<String,String>, dependencies : &mut Vec<String>) -> bool {
+  //! creates the preloads for all the requires and internal modules, removing the `--#if` blocks, replacing
+  //! the defines and resolving the `@references` in each one. The names of the dependencies that are
+  //! referenced are added to `dependencies`.
+  //!
+  //! needs to be done after the dependencies so references to them can be resolved. Returns false if any
+  //! file couldn't be read or had references that don't exist.
+  let references = match definition.references() {
+    None => { return true; },
+    Some(references) => { references }
+  };
+
+  // the same file can be used more than once, only needs one preload.
+  let mut files : Vec<String> = references.values().cloned().collect();
+  files.sort();
+  files.dedup();
+
+  // names everything first, so the files can reference each other.
+  for file in files.iter() {
+    let preload_text :String = processing::gen::create_random_preload_name(&definition.name);
+    preload_hash.insert(file.clone(),preload_text);
+  }
+
+  let defines = processing::defines::collect(definition,settings);
+  let resolver = Resolver::new(definition,preload_hash);
+  let mut unresolved : Vec<UnresolvedReference> = Vec::new();
+  let mut success : bool = true;
+
+  // which modules each file references, all of them for the order and the ones used while loading for the cycles.
+  let mut preloads : HashMap<String,String> = HashMap::new();
+  let mut modules : HashMap<String,Vec<String>> = HashMap::new();
+  let mut load_modules : HashMap<String,Vec<String>> = HashMap::new();
+
+  for file in files.iter() {
+    // builds the path to the file
+    let src_path = require_path(path,file);
+    let contents : String = match processing::buffer::preprocess(&library::luafile::get_raw_file_contents(&src_path),&src_path,&defines)
+      .and_then(|contents| processing::defines::substitute(&contents,&src_path,&defines)) {
+      Ok(contents) => { contents }
+      Err(error) => { output_error!("{}",error); success = false; continue; }
+    };
+
+    match resolver.resolve(&contents,&src_path,file) {
+      Err(error) => {
+        output_error!("Error reading {}:{}: {}",Red.paint(src_path.display().to_string()),error.line,Yellow.paint(error.message));
+        success = false;
+      }
+      Ok(resolved) => {
+        unresolved.extend(resolved.unresolved);
+        modules.insert(file.clone(),resolved.modules);
+        load_modules.insert(file.clone(),resolved.load_modules);
+        for name in resolved.dependencies { if !dependencies.contains(&name) { dependencies.push(name); } }
 
+        let preload_text : &String = &preload_hash[file];
         output_debug!("Loading {} into {}",&src_path.display().to_string(),&preload_text);
-        array_of_preloads.push(library::luafile::create_preload_string(&src_path,&preload_text));
+        preloads.insert(file.clone(),library::luafile::create_preload_string(&resolved.source,preload_text,&chunk_name(file),settings.debug_build()));
       }
     }
   }
-}
 
-pub fn dependencies(dest : &PathBuf, definition : &LibraryDefinition,array_of_preloads : &mut Vec<String>,preload_hash : &mut HashMap<String,String>) {
-  if let Some(ref hash) = definition.dependencies {
+  // requiring a module that is still loading fails with "loop or previous error loading module"
+  for cycle in processing::order::find_cycles(&files,&load_modules) {
+    output_error!("Require cycle, these modules require each other while loading: {}",Red.paint(cycle.join(" -> ")));
+    success = false;
+  }
 
-    for(name,blob) in hash.iter() {
-      let library_name : String = if let Some(lname) = blob.get("name") { lname.clone() } else { name.clone() };
+  for file in processing::order::sort(&files,&modules) {
+    if let Some(preload) = preloads.remove(&file) { array_of_preloads.push(preload); }
+  }
 
-      // gets the required version / with error checking on MAX!
-      let required_version : Version = if let Some(ver) = blob.get("version") { 
-        if let Some(version) = Version::from_str(ver) { version } 
-        else { 
-          output_error!("Malformed version requirement in dependency for {}: {}, using \"{}\" instead.",Blue.paint(library_name.to_string()),Red.paint(ver.to_string()),Yellow.paint("*"));
-          Version::from_str("*").unwrap()
-        }
-      } else { Version::from_str("*").unwrap() };
+  processing::references::report_unresolved(&unresolved,settings.allow_unresolved) && success
+}
 
-      let reference_name : String = name.clone();
+pub fn require_path(path : &Path, file : &str) -> PathBuf {
+  //! builds the path to the source file of a require, `src.tools` is `src/tools.lua` inside the library.
+  let mut src_path = path.to_path_buf();
+  let temp_vector : Vec<&str> = file.split(".").collect();
+  for cc in 0..temp_vector.len() { 
+    if cc == (temp_vector.len()-1) { 
+      src_path.push(format!("{}.{}",temp_vector[cc],"lua"));
+    } else {
+      src_path.push(temp_vector[cc]);
+    }
+  }
+  src_path
+}
 
-      if let Some(dependancy_path) = get_library_path(&library_name,&required_version) {
-        output_debug!("Found library at {}",Blue.paint(dependancy_path.display().to_string()));
+pub fn chunk_name(file : &str) -> String {
+  //! the name lua reports for a require's source in debug builds, `src.tools` is `src/tools.lua`.
+  format!("{}.lua",file.replace(".","/"))
+}
 
-        match super::super::compile(&dependancy_path,&dest,true,&None) {
-          Err(error) => {
-            output_error!("Error compiling dependancy {}: {}",Blue.paint(library_name.clone()),Yellow.paint(error.to_string()));
-          },
-          Ok(compiled_path) => { 
-            // nead to insert the source into a preload
-            let preload_text :String = processing::gen::create_random_preload_name(&definition.name);
-            preload_hash.insert(reference_name.clone(),preload_text.clone());
+pub fn dependencies(dest : &Path, definition : &LibraryDefinition, settings : &Settings, parents : &[String], array_of_preloads : &mut Vec<String>, preload_hash : &mut HashMap<String,String>) -> Vec<ResolvedDependency> {
+  //! compiles all the dependencies of the library on the thread pool, and then adds them to the preloads.
+  //! returns each dependency that compiled, with the ones it depends on.
+  //!
+  //! the results are processed in name order, so the output (and the order of the preloads) is the same
+  //! no matter which dependency finished compiling first. `parents` are the libraries that are being
+  //! compiled because of this one, to find dependency cycles.
+  let mut resolved : Vec<ResolvedDependency> = Vec::new();
 
-            array_of_preloads.push(library::luafile::create_preload_string(&compiled_path,&preload_text));
-          }
+  if let Some(ref hash) = definition.dependencies {
+
+    let mut names : Vec<&String> = hash.keys().collect();
+    names.sort();
+
+    let mut chain : Vec<String> = parents.to_vec();
+    chain.push(definition.name.clone());
+
+    let mut jobs = Vec::new();
+    for name in names.iter() {
+      let dependency : &Dependency = &hash[*name];
+      let library_name : String = dependency.library_name(name);
+      let version : Option<String> = dependency.version.clone();
+      let features : FeatureRequest = dependency.feature_request();
+      let target : Option<String> = dependency.target.clone();
+      let source : Option<PathBuf> = definition.dependency_source(&library_name,dependency);
+      let dest : PathBuf = dest.to_path_buf();
+      let chain : Vec<String> = chain.clone();
+      let settings : Settings = settings.clone();
+
+      jobs.push((name.to_string(),move || compile_dependency(&dest,&settings,&chain,&library_name,&version,&features,&target,&source)));
+    }
+
+    for (name,result) in names.iter().zip(processing::jobs::run(jobs,settings.jobs)) {
+      match result {
+        // already reported
+        None => { }
+        Some(Err(error)) => { output_error!("{}",error); }
+        Some(Ok((preload_text,preload_string,mut dependency))) => { 
+          preload_hash.insert(name.to_string(),preload_text);
+          array_of_preloads.push(preload_string);
+          // known by its key in `[dependencies]`, which isn't always the library's name
+          dependency.name = name.to_string();
+          resolved.push(dependency);
         }
-      } else { output_error!("Cannot find library {} ",Red.paint(library_name.clone())); }
+      }
     }
 
   }
+
+  resolved
 }
 
-// returns the latest matching version available.
-pub fn get_library_latest_version(library_name:&str, version:&Version) -> Option<Version> {
-  // checks locally.
-  if let Some(value) = lpsettings::get_value("library.local-folder") { 
-    let libraries : HashMap<String,PathBuf> = local::library::get_local_libraries(&PathBuf::from(&value));
-    // it we find the library locally
-    if let Some(path) = libraries.get(library_name) { 
-      // now we need to check if it has the right version inside it.
-      let version_tags = get_tag_names(&path);
-      match version.latest_compatible(&version_tags) {
-        None => { output_error!("No version found matching {} requirements.",Red.paint(version.to_string()));}
-        Some(matching_version) => { 
-          output_debug!("Found {} locally.",Yellow.paint(matching_version.clone()));
-          return Version::from_str(matching_version);
+#[allow(clippy::too_many_arguments)]
+fn compile_dependency(dest : &PathBuf, settings : &Settings, parents : &[String], library_name : &str, version : &Option<String>, features : &FeatureRequest, target : &Option<String>, source : &Option<PathBuf>) -> Result<(String,String,ResolvedDependency),String> {
+  //! compiles a single dependency and returns the preload name, the preload string and what was used.
+  //!
+  //! a dependency with a `source` (a `path` or a member of the workspace) is built from there, it has to match the
+  //! version if the entry has one.
+
+  // a library that is already being compiled further up would never finish
+  if let Some(position) = parents.iter().position(|parent| parent == library_name) {
+    return Err(format!("Dependency cycle: {}",Red.paint(format!("{} -> {}",parents[position..].join(" -> "),library_name))));
+  }
+
+  // gets the required version / with error checking on MAX!
+  let required_version : Version = if let Some(ref ver) = *version { 
+    if let Some(version) = Version::from_str(ver) { version } 
+    else { 
+      output_error!("Malformed version requirement in dependency for {}: {}, using \"{}\" instead.",Blue.paint(library_name.to_string()),Red.paint(ver.to_string()),Yellow.paint("*"));
+      Version::from_str("*").unwrap()
+    }
+  } else { Version::from_str("*").unwrap() };
+
+  let library_path : Option<PathBuf> = match *source {
+    Some(ref source) => { Some(source.clone()) }
+    None => { get_library_path(library_name,&required_version) }
+  };
+
+  // checked before compiling, a dependency that isn't the expected version shouldn't be built at all
+  if let (Some(source),&Some(_)) = (source,version) {
+    if let Some(dependency_definition) = library::luafile::get_lualib_settings(source) {
+      if !dependency_definition.version.is_compatible_with(&required_version) {
+        return Err(format!("{} is {} in {}, which doesn't match the requirement {}",Blue.paint(library_name.to_string()),Red.paint(dependency_definition.version.to_string()),source.display(),Yellow.paint(required_version.to_string())));
+      }
+    }
+  }
+
+  match library_path {
+    None => { Err(format!("Cannot find library {} ",Red.paint(library_name.to_string()))) }
+    Some(dependancy_path) => {
+      output_debug!("Found library at {}",Blue.paint(dependancy_path.display().to_string()));
+
+      match super::super::compile_with_parents(&dependancy_path,dest,true,&None,features,target,settings,parents) {
+        Err(error) => {
+          Err(format!("Error compiling dependancy {}: {}",Blue.paint(library_name.to_string()),Yellow.paint(error.to_string())))
+        },
+        Ok((compiled_paths,dependencies)) => { 
+          // a dependency only builds one file
+          let compiled_path : PathBuf = match compiled_paths.first() {
+            Some(compiled_path) => { compiled_path.clone() }
+            None => { return Err(format!("Dependancy {} didn't compile to anything",Red.paint(library_name.to_string()))); }
+          };
+          // nead to insert the source into a preload
+          let preload_text :String = processing::gen::create_random_preload_name(&parents[parents.len() - 1]);
+          let chunk_name : String = if let Some(file_name) = compiled_path.file_name() { file_name.to_string_lossy().to_string() } else { library_name.to_string() };
+
+          // another thread could be writing the same dependency to the cache
+          let contents : String = {
+            let lock = cache_lock(&compiled_path);
+            let _guard = lock.lock().unwrap_or_else(|error| error.into_inner());
+            library::luafile::get_raw_file_contents(&compiled_path)
+          };
+
+          let preload_string : String = library::luafile::create_preload_string(&contents,&preload_text,&chunk_name,settings.debug_build());
+          let version : String = match library::luafile::get_lualib_settings(&dependancy_path) {
+            Some(dependency_definition) => { dependency_definition.version.to_string() }
+            None => { required_version.to_string() }
+          };
+          Ok((preload_text,preload_string,ResolvedDependency { name : library_name.to_string(), version, path : dependancy_path.clone(), dependencies }))
         }
       }
     }
   }
-  None
+}
+
+pub fn cache_lock(path : &PathBuf) -> Arc<Mutex<()>> {
+  //! gets the lock for a path in the cache, so only one thread at a time clones into the same
+  //! `<name>-<version>` folder, or writes and reads the same compiled file.
+  let mut locks = CACHE_LOCKS.lock().unwrap_or_else(|error| error.into_inner());
+  locks.retain(|_,lock| lock.strong_count() > 0);
+
+  if let Some(lock) = locks.get(path).and_then(|lock| lock.upgrade()) { return lock; }
+
+  let lock = Arc::new(Mutex::new(()));
+  locks.insert(path.clone(),Arc::downgrade(&lock));
+  lock
+}
+
+// returns the latest matching version available.
+pub fn get_library_latest_version(library_name:&str, version:&Version) -> Option<Version> {
+  get_library_tag(library_name,version).and_then(|(tag,_)| Version::from_str(&tag))
 }
 
 pub fn get_library_path_git(library_name:&str) -> Option<PathBuf> {
@@ -133,16 +304,8 @@ pub fn get_library_path_git(library_name:&str) -> Option<PathBuf> {
   None
 }
 
-pub fn get_library_path(library_name:&str, version:&Version) -> Option<PathBuf> {
-  //! looks for the correct path to the library requested.
-  //!
-  //! First it will look in the local area, check for git tags, and then mark the best matching tag.
-  //! Then it checks remotely (not yet implemented)
-  //!
-  //! Finally it then clones, checkouts the tag, and then returns that path to be used.
-
-  // checks if it is locally
-  let mut best_local_version : Option<(String,PathBuf)> = None;
+pub fn get_library_tag(library_name:&str, version:&Version) -> Option<(String,PathBuf)> {
+  //! the tag that best matches the version, and the local repository it is in. Doesn't clone anything.
 
   // checks locally.
   if let Some(value) = lpsettings::get_value("library.local-folder") { 
@@ -150,12 +313,12 @@ pub fn get_library_path(library_name:&str, version:&Version) -> Option<PathBuf>
     // it we find the library locally
     if let Some(path) = libraries.get(library_name) { 
       // now we need to check if it has the right version inside it.
-      let version_tags = get_tag_names(&path);
+      let version_tags = get_tag_names(path);
       match version.latest_compatible(&version_tags) {
         None => { output_error!("No version found matching {} requirements.",Red.paint(version.to_string()));}
         Some(matching_version) => { 
           output_debug!("Found {} locally.",Yellow.paint(matching_version.clone()));
-          best_local_version = Some((matching_version.to_string(),path.clone())); 
+          return Some((matching_version.to_string(),path.clone())); 
         }
       }
     }
@@ -164,13 +327,34 @@ pub fn get_library_path(library_name:&str, version:&Version) -> Option<PathBuf>
   // checks remotely ....
   output_debug!("remote libraries not yet implemented.");
 
+  None
+}
+
+pub fn cache_path(library_name:&str, tag:&str) -> PathBuf {
+  //! the folder in the cache a tag of a library is cloned into.
+  let mut cloned_path = if let Ok(path) = lpsettings::get_settings_folder() { path } else { PathBuf::from(".") };
+  cloned_path.push(lpsettings::get_value_or("core.cache","cache"));
+  cloned_path.push(format!("{}-{}",&library_name,&tag));
+  cloned_path
+}
+
+pub fn get_library_path(library_name:&str, version:&Version) -> Option<PathBuf> {
+  //! looks for the correct path to the library requested.
+  //!
+  //! First it will look in the local area, check for git tags, and then mark the best matching tag.
+  //! Then it checks remotely (not yet implemented)
+  //!
+  //! Finally it then clones, checkouts the tag, and then returns that path to be used.
+
   // finds the path
-  if let Some((matching_version,path)) = best_local_version {
+  if let Some((matching_version,path)) = get_library_tag(library_name,version) {
     output_debug!("Using {} for the requirement {}",Yellow.paint(matching_version.clone()),Blue.paint(version.to_string()));
-    let mut cloned_path = if let Ok(path) = lpsettings::get_settings_folder() { path } else { PathBuf::from(".") };
+    let cloned_path : PathBuf = cache_path(library_name,&matching_version);
+
+    // other threads could be cloning the same version right now.
+    let lock = cache_lock(&cloned_path);
+    let _guard = lock.lock().unwrap_or_else(|error| error.into_inner());
 
-    cloned_path.push(lpsettings::get_value_or("core.cache","cache"));
-    cloned_path.push(format!("{}-{}",&library_name,&matching_version.to_string()));
     if cloned_path.exists() { 
       output_debug!("{} already exists, using existing.",Blue.paint(cloned_path.display().to_string()));
       return Some(cloned_path); 
@@ -194,19 +378,17 @@ pub fn get_library_path(library_name:&str, version:&Version) -> Option<PathBuf>
 
 // GIT STUFF
 
-fn get_tag_names(src : &PathBuf) -> Vec<String> {
+pub fn get_tag_names(src : &Path) -> Vec<String> {
   //! gets list of all the tags for the given repository path
 
   let mut tags : Vec<String> = Vec::new();
 
-  match git2::Repository::open(&src.display().to_string()) {
+  match git2::Repository::open(src.display().to_string()) {
     Err(error) => { output_error!("Error getting tags for repository {}: {}",Red.paint(src.display().to_string()),Yellow.paint(error.to_string())); }
     Ok(repo) => {  
       if let Ok(tags_from_git2) = repo.tag_names(None) {
-        for option_tags in tags_from_git2.iter() {
-          if let Some(tag) = option_tags {
-            tags.push(tag.to_string());
-          }
+        for tag in tags_from_git2.iter().flatten() {
+          tags.push(tag.to_string());
         }
       }
     }
@@ -215,19 +397,38 @@ fn get_tag_names(src : &PathBuf) -> Vec<String> {
   tags
 }
 
-fn clone_repository(src : &PathBuf, des : &PathBuf) -> Result<(),git2::Error> {
+pub fn get_definition_at_tag(src : &Path, tag : &str) -> Option<LibraryDefinition> {
+  //! reads the library definition file as it was at the given tag, without checking anything out.
+
+  match git2::Repository::open(src.display().to_string()) {
+    Err(error) => { output_error!("Error opening repository {}: {}",Red.paint(src.display().to_string()),Yellow.paint(error.to_string())); None }
+    Ok(repo) => {
+      match repo.revparse_single(&format!("refs/tags/{}:{}",&tag,LIBDEFFILE)) {
+        Err(error) => { output_debug!("No {} found at tag {}: {}",Yellow.paint(LIBDEFFILE),Red.paint(tag.to_string()),error.to_string()); None }
+        Ok(object) => {
+          match object.as_blob() {
+            None => { None }
+            Some(blob) => { library::luafile::parse_lualib_settings(&String::from_utf8_lossy(blob.content())) }
+          }
+        }
+      }
+    }
+  }
+}
+
+fn clone_repository(src : &Path, des : &Path) -> Result<(),git2::Error> {
   //! copies the repository form the SRC to the DES
 
-  match git2::Repository::clone(&src.display().to_string(),&des.display().to_string()) {
+  match git2::Repository::clone(&src.display().to_string(),des.display().to_string()) {
     Err(error) => { Err(error) }
     Ok(_repo) => { Ok( () ) }
   }
 }
 
-fn checkout_tag(src :&PathBuf, tag : &str) -> Result<(),git2::Error> {
+fn checkout_tag(src :&Path, tag : &str) -> Result<(),git2::Error> {
   //! check out the src repository at the given tag.
 
-  match git2::Repository::open(&src.display().to_string()) {
+  match git2::Repository::open(src.display().to_string()) {
     Err(error) => { Err(error) }
     Ok(repo) => {  
       
@@ -235,7 +436,7 @@ fn checkout_tag(src :&PathBuf, tag : &str) -> Result<(),git2::Error> {
         Err(error) => { Err(error) }
         Ok(reference) => { 
 
-          match repo.set_head(&reference.name().unwrap()) {
+          match repo.set_head(reference.name().unwrap()) {
             Err(error) => { Err(error) }
             Ok(_) => { 
 
@@ -0,0 +1,211 @@
+//! build time constants, substituted into the source files before the `@references` are resolved.
+//!
+//! - `$NAME` in code is replaced with the value as a lua literal, so `"text"`, `12` or `true`.
+//! - `${NAME}` anywhere, including strings and comments, is replaced with the value as it is.
+//!
+//! the values come from the built in `NAME`, `VERSION`, `LOVE` and `PROFILE`, then `[defines]` in the definition,
+//! the defines of the profile and then `--define KEY=VALUE`, each one replacing the one before.
+
+use ansi_term::Colour::{Red,Yellow};
+use regex;
+use toml;
+
+use std::path::Path;
+use std::collections::HashMap;
+
+use library::lualibdef::LibraryDefinition;
+use processing::settings::Settings;
+use processing::lexer::{self,TokenKind};
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Define {
+  Text(String),
+  Number(String),
+  Boolean(bool),
+  Nil,
+}
+
+impl Define {
+  pub fn from_str(value : &str) -> Define {
+    //! values from the command line, anything that isn't a boolean or a number is text.
+    match value {
+      "true" => { Define::Boolean(true) }
+      "false" => { Define::Boolean(false) }
+      "nil" => { Define::Nil }
+      _ => { if is_lua_number(value) { Define::Number(value.to_string()) } else { Define::Text(value.to_string()) } }
+    }
+  }
+
+  pub fn from_toml(value : &toml::Value) -> Option<Define> {
+    match *value {
+      toml::Value::String(ref text) => { Some(Define::Text(text.clone())) }
+      toml::Value::Integer(number) => { Some(Define::Number(number.to_string())) }
+      toml::Value::Float(number) if number.is_finite() => { Some(Define::Number(number.to_string())) }
+      toml::Value::Boolean(boolean) => { Some(Define::Boolean(boolean)) }
+      _ => { None }
+    }
+  }
+
+  pub fn to_lua(&self) -> String {
+    match *self {
+      Define::Text(ref text) => { format!("\"{}\"",text.replace("\\","\\\\").replace("\"","\\\"").replace("\n","\\n")) }
+      Define::Number(ref number) => { number.clone() }
+      Define::Boolean(boolean) => { boolean.to_string() }
+      Define::Nil => { "nil".to_string() }
+    }
+  }
+
+  pub fn to_raw(&self) -> String {
+    match *self {
+      Define::Text(ref text) | Define::Number(ref text) => { text.clone() }
+      Define::Boolean(boolean) => { boolean.to_string() }
+      Define::Nil => { String::new() }
+    }
+  }
+}
+
+fn is_lua_number(value : &str) -> bool {
+  //! checks for a number lua can read, like `12`, `-0.5`, `1e10` or `0xff`. Not `inf` or `nan`, rust reads
+  //! those but in lua they would be names.
+  let value : &str = value.strip_prefix('-').unwrap_or(value);
+  let (digits,exponent,value) : (fn(char) -> bool,[char; 2],&str) = if value.starts_with("0x") || value.starts_with("0X") {
+    (|c : char| c.is_ascii_hexdigit(),['p','P'],&value[2..])
+  } else {
+    (|c : char| c.is_ascii_digit(),['e','E'],value)
+  };
+
+  let (mantissa,power) : (&str,Option<&str>) = match value.find(&exponent[..]) {
+    Some(position) => { (&value[..position],Some(&value[position + 1..])) }
+    None => { (value,None) }
+  };
+
+  // at least one digit, and only one `.`
+  let mut parts = mantissa.splitn(2,".");
+  let whole : &str = parts.next().unwrap();
+  let fraction : &str = parts.next().unwrap_or("");
+  if whole.len() + fraction.len() == 0 || !whole.chars().chain(fraction.chars()).all(digits) { return false; }
+
+  match power {
+    None => { true }
+    Some(power) => {
+      let power : &str = if power.starts_with("+") || power.starts_with("-") { &power[1..] } else { power };
+      !power.is_empty() && power.chars().all(|c| c.is_ascii_digit())
+    }
+  }
+}
+
+pub fn collect(definition : &LibraryDefinition, settings : &Settings) -> HashMap<String,Define> {
+  let mut defines : HashMap<String,Define> = HashMap::new();
+  defines.insert("NAME".to_string(),Define::Text(definition.name.clone()));
+  defines.insert("VERSION".to_string(),Define::Text(definition.version.to_string()));
+  defines.insert("LOVE".to_string(),match definition.love { Some(ref love) => { Define::Text(love.to_string()) } None => { Define::Nil } });
+  defines.insert("PROFILE".to_string(),Define::Text(settings.profile_name.clone()));
+
+  if let Some(ref values) = definition.defines {
+    for (key,value) in values.iter() {
+      match Define::from_toml(value) {
+        Some(define) => { defines.insert(key.clone(),define); }
+        None => { output_error!("The define {} has to be a string, number or boolean",Red.paint(key.clone())); }
+      }
+    }
+  }
+
+  // the profile's defines
+  if let Some(values) = settings.defines() {
+    for (key,value) in values.iter() {
+      match Define::from_toml(value) {
+        Some(define) => { defines.insert(key.clone(),define); }
+        None => { output_error!("The define {} in the profile {} has to be a string, number or boolean",Red.paint(key.clone()),&settings.profile_name); }
+      }
+    }
+  }
+
+  // `--define`, each one is `KEY=VALUE`
+  for line in settings.command_defines.iter() {
+    let mut split = line.splitn(2,"=");
+    let key : &str = split.next().unwrap().trim();
+    if key.is_empty() { continue; }
+    let value : Define = match split.next() { Some(value) => { Define::from_str(value.trim()) } None => { Define::Boolean(true) } };
+    defines.insert(key.to_string(),value);
+  }
+
+  defines
+}
+
+pub fn substitute(contents : &str, src_path : &Path, defines : &HashMap<String,Define>) -> Result<String,String> {
+  //! replaces all the defines in a source file, fails on a `$NAME` that isn't defined.
+
+  // `${NAME}` first, it can be anywhere so it doesn't need the lexer.
+  let re = regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+  let contents : String = re.replace_all(contents,|captures : &regex::Captures| {
+    match defines.get(&captures[1]) {
+      Some(define) => { define.to_raw() }
+      None => {
+        output_warning!("{} isn't defined in {}",Yellow.paint(captures[0].to_string()),Yellow.paint(src_path.display().to_string()));
+        captures[0].to_string()
+      }
+    }
+  }).into_owned();
+
+  // `$NAME` only in code, a `$` isn't valid lua so there isn't anything else it could be.
+  let tokens = match lexer::tokenize(&contents) {
+    Ok(tokens) => { tokens }
+    // the reference resolver reports this with the line
+    Err(_) => { return Ok(contents); }
+  };
+
+  let mut source : String = String::new();
+  let mut last : usize = 0;
+  for (i,token) in tokens.iter().enumerate() {
+    if token.kind != TokenKind::Symbol || token.text != "$" { continue; }
+    let name = match tokens.get(i + 1) {
+      Some(name) if name.kind == TokenKind::Name && name.start == token.end => { name }
+      _ => { continue; }
+    };
+
+    match defines.get(&name.text) {
+      None => { return Err(format!("${} isn't defined, in {}:{}",name.text,src_path.display(),token.line)); }
+      Some(define) => {
+        source.push_str(&contents[last..token.start]);
+        source.push_str(&define.to_lua());
+        last = name.end;
+      }
+    }
+  }
+  source.push_str(&contents[last..]);
+
+  Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+  use library::luafile;
+  use processing::settings::Settings;
+  use super::{collect,Define};
+
+  #[test]
+  fn numbers_from_the_command_line() {
+    for number in ["12", "-3", "0.5", ".5", "1.", "1e10", "2.5E-3", "0xff", "0x1p4", "-0X.8P+2"].iter() {
+      assert_eq!(Define::from_str(number),Define::Number(number.to_string()),"{}",number);
+    }
+    // rust would read these as numbers, lua wouldn't
+    for text in ["inf", "-inf", "nan", "NaN", "infinity", "+1", "1e", "0x", ".", "-", "1.2.3", "1_000", "0xg", "12px"].iter() {
+      assert_eq!(Define::from_str(text),Define::Text(text.to_string()),"{}",text);
+    }
+  }
+
+  #[test]
+  fn command_line_defines_win() {
+    let definition = luafile::parse_lualib_settings("name = \"a\"\nuser = \"u\"\nauthor = \"u\"\nversion = \"1.0.0\"\n[defines]\nDEBUG = false\nLEVEL = 3\n").unwrap();
+    let command_defines : Vec<String> = vec!["DEBUG=true".to_string(),"MODE = fast".to_string(),"FLAG".to_string(),"=1".to_string()];
+    let settings : Settings = Settings { command_defines, .. Settings::default() };
+
+    let defines = collect(&definition,&settings);
+    assert_eq!(defines["DEBUG"],Define::Boolean(true));
+    assert_eq!(defines["MODE"],Define::Text("fast".to_string()));
+    assert_eq!(defines["FLAG"],Define::Boolean(true));
+    assert_eq!(defines["LEVEL"],Define::Number("3".to_string()));
+    assert_eq!(defines["PROFILE"],Define::Text("dev".to_string()));
+    assert!(!defines.contains_key(""));
+  }
+}
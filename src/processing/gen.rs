@@ -1,9 +1,12 @@
 use rand;
 use rand::Rng;
 
-use std::env;
 
-use library::lualibdef::LibraryDefinition;
+use lpsettings;
+use num_cpus;
+
+use library::lualibdef::{LibraryDefinition,Target};
+use processing::settings::Settings;
 
 pub fn create_random_preload_name(library_name:&str) -> String {
   let mut additative : String = "".to_string();
@@ -16,15 +19,42 @@ pub fn create_random_preload_name(library_name:&str) -> String {
   format!("{}-{}",&library_name,&additative)
 }
 
-pub fn compiled_file_name(def : &LibraryDefinition, dep : bool) -> String {
-  if dep { return format!("{}-{}.{}",&def.name,&def.version.to_string(),"lua"); }
+pub fn compiled_file_name(def : &LibraryDefinition, dep : bool, target : &Option<Target>, settings : &Settings) -> String {
+  if dep {
+    // the same dependency with different features or targets can't share a file
+    let mut name : String = format!("{}-{}",&def.name,&def.version.to_string());
+    if !def.enabled_features.is_empty() { name = format!("{}+{}",name,def.enabled_features.join("+")); }
+    if let Some(ref target) = *target { name = format!("{}@{}",name,&target.name); }
+    format!("{}.{}",name,"lua")
+  }
+  else if let Some(ref target) = *target {
+    // each target needs its own name, so `--compiled-name` isn't used
+    let name : String = target.compiled_name.clone().unwrap_or(format!("{}-{}",&def.name,&target.name));
+    if settings.name_with_version() { return format!("{}-{}.{}",name,&def.version.to_string(),"lua"); }
+    format!("{}.{}",name,"lua")
+  }
   else {
-    if let Ok(new_name) = env::var("LMAKE_COMPILE_NAME") {
-      return format!("{}.{}",new_name,"lua");
-    } else if let Ok(_) = env::var("LMAKE_COMPILE_WITH_VERSION_IN_NAME") { 
-      return format!("{}-{}.{}",&def.name,&def.version.to_string(),"lua");
+    if let Some(new_name) = settings.compiled_name() {
+      format!("{}.{}",new_name,"lua")
+    } else if settings.name_with_version() { 
+      format!("{}-{}.{}",&def.name,&def.version.to_string(),"lua")
     } else { 
-      return format!("{}.{}",&def.name,"lua");
+      format!("{}.{}",&def.name,"lua")
     }
   }
+}
+
+pub fn job_count(jobs : Option<usize>) -> usize {
+  //! how many libraries / dependencies can be compiled at the same time.
+  //!
+  //! uses the `--jobs` switch first, then the `lmake.jobs` setting, and if neither is set
+  //! it uses the number of cpus available.
+  let jobs : Option<usize> = if jobs.is_some() { jobs }
+    else if let Some(jobs) = lpsettings::get_value("lmake.jobs") { jobs.parse().ok() }
+    else { None };
+
+  match jobs {
+    Some(count) if count > 0 => { count }
+    _ => { num_cpus::get() }
+  }
 }
\ No newline at end of file
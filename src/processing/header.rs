@@ -0,0 +1,166 @@
+//! the comment block at the top of the compiled library.
+//!
+//! `header` in the definition can be a template (or the path to a file with one), with placeholders like
+//! `{name}` and `{license}`. Lines that aren't already comments are turned into comments.
+
+use ansi_term::Colour::Yellow;
+use regex;
+
+use std::path::PathBuf;
+
+use library;
+use library::lualibdef::LibraryDefinition;
+use processing::build;
+
+static PLACEHOLDERS : [&str; 12] = [
+  "name", "user", "version", "author", "email", "description", "license", "homepage", "love", "date", "commit", "lmake",
+];
+
+pub fn render(path : &PathBuf, definition : &LibraryDefinition) -> String {
+  match definition.header {
+    None => { default_header(definition) }
+    Some(ref header) => {
+      // a file in the library, otherwise the template itself
+      let mut header_path : PathBuf = path.clone();
+      header_path.push(header);
+      let template : String = if !header.contains('\n') && header_path.is_file() { library::luafile::get_raw_file_contents(&header_path) } else { header.clone() };
+
+      let mut text : String = String::new();
+      for line in fill(&template,path,definition).lines() {
+        if line.trim_start().starts_with("--") { text = format!("{}{}\n",text,line); }
+        else if line.trim().is_empty() { text = format!("{}--\n",text); }
+        else { text = format!("{}-- {}\n",text,line); }
+      }
+      format!("{}\n",text)
+    }
+  }
+}
+
+fn default_header(definition : &LibraryDefinition) -> String {
+  let mut library : String = format!("-- {}/{} {}\n",&definition.user,&definition.name,&definition.version.to_string());
+  if let Some(ref description) = definition.description { library = format!("{}-- {}\n",library,description.replace("\n"," ")); }
+  library = format!("{}-- by {}\n",library,authors(definition));
+  if let Some(ref license) = definition.license { library = format!("{}-- license: {}\n",library,license); }
+  if let Some(ref homepage) = definition.homepage { library = format!("{}-- homepage: {}\n",library,homepage); }
+
+  format!("{}\
+    -- built with {} ({}) <{}>\n\
+    -- a tool for compiling lua libraries from multiple source files and dependencies\n\n",
+
+    library,
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_VERSION"),
+    "https://github.com/snsvrno/lmake-rs"
+  )
+}
+
+fn fill(template : &str, path : &PathBuf, definition : &LibraryDefinition) -> String {
+  //! replaces the placeholders, anything unknown is left alone.
+  for placeholder in unknown_placeholders(template) {
+    output_warning!("Unknown placeholder {} in the header, the known ones are {}",Yellow.paint(format!("{{{}}}",placeholder)),PLACEHOLDERS.join(", "));
+  }
+
+  let re = regex::Regex::new(r"\{([a-z]+)\}").unwrap();
+  re.replace_all(template,|captures : &regex::Captures| {
+    match &captures[1] {
+      "name" => { definition.name.clone() }
+      "user" => { definition.user.clone() }
+      "version" => { definition.version.to_string() }
+      "author" => { authors(definition) }
+      "email" => { definition.email.clone().unwrap_or_default() }
+      "description" => { definition.description.clone().unwrap_or_default() }
+      "license" => { definition.license.clone().unwrap_or_default() }
+      "homepage" => { definition.homepage.clone().or(definition.upstream.clone()).unwrap_or_default() }
+      "love" => { definition.love.as_ref().map(|love| love.to_string()).unwrap_or_default() }
+      "date" => { date(build::timestamp()) }
+      "commit" => { build::commit(path).map(|(commit,_)| commit).unwrap_or_default() }
+      "lmake" => { env!("CARGO_PKG_VERSION").to_string() }
+      _ => { captures[0].to_string() }
+    }
+  }).into_owned()
+}
+
+fn unknown_placeholders(template : &str) -> Vec<String> {
+  //! every `{name}` in the template that isn't a placeholder, once each. Typos like `{Name}` count too.
+  let re = regex::Regex::new(r"\{([A-Za-z_-]+)\}").unwrap();
+  let mut unknown : Vec<String> = Vec::new();
+  for captures in re.captures_iter(template) {
+    let name : &str = &captures[1];
+    if !PLACEHOLDERS.contains(&name) && !unknown.iter().any(|placeholder| placeholder == name) { unknown.push(name.to_string()); }
+  }
+  unknown
+}
+
+fn authors(definition : &LibraryDefinition) -> String {
+  if let Some(ref authors) = definition.authors { authors.join(", ") } else { definition.author.clone() }
+}
+
+fn date(timestamp : u64) -> String {
+  //! the `YYYY-MM-DD` of a unix timestamp.
+  // days to a civil date, from http://howardhinnant.github.io/date_algorithms.html
+  let days : i64 = (timestamp / 86400) as i64 + 719468;
+  let era : i64 = days / 146097;
+  let day_of_era : i64 = days - era * 146097;
+  let year_of_era : i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year : i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let mp : i64 = (5 * day_of_year + 2) / 153;
+  let day : i64 = day_of_year - (153 * mp + 2) / 5 + 1;
+  let month : i64 = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year : i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  format!("{:04}-{:02}-{:02}",year,month,day)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use library::luafile;
+  use library::lualibdef::LibraryDefinition;
+  use super::{date,fill,render,unknown_placeholders};
+
+  fn definition(extra : &str) -> LibraryDefinition {
+    luafile::parse_lualib_settings(&format!("name = 'shapes'\nuser = 'someone'\nauthor = 'me'\nversion = '1.2.0'\nlicense = 'MIT'\n{}",extra)).unwrap()
+  }
+
+  #[test]
+  fn placeholders() {
+    let path : PathBuf = PathBuf::from("/nowhere");
+    let definition = definition("authors = ['me', 'you']\nupstream = 'https://example.com'");
+    assert_eq!(fill("{name} {version} by {author} ({license}) {homepage}",&path,&definition),"shapes 1.2.0 by me, you (MIT) https://example.com");
+    // missing values are empty, unknown ones are left alone
+    assert_eq!(fill("[{email}] {nope} {Name}",&path,&definition),"[] {nope} {Name}");
+  }
+
+  #[test]
+  fn unknown_names() {
+    assert_eq!(unknown_placeholders("{name} {nope} {Name} {nope} {home_page} {version}"),vec!["nope","Name","home_page"]);
+    assert!(unknown_placeholders("local t = {} {name}").is_empty());
+  }
+
+  #[test]
+  fn rendered_template() {
+    let path : PathBuf = PathBuf::from("/nowhere");
+    assert_eq!(render(&path,&definition("header = \"{name} v{version}\\n\\n-- already a comment\"")),"-- shapes v1.2.0\n--\n-- already a comment\n\n");
+
+    // without a template it is the default one
+    let default : String = render(&path,&definition(""));
+    assert!(default.starts_with("-- someone/shapes 1.2.0\n-- by me\n-- license: MIT\n"));
+  }
+
+  #[test]
+  fn dates() {
+    assert_eq!(date(0),"1970-01-01");
+    assert_eq!(date(86399),"1970-01-01");
+    // years and months changing
+    assert_eq!(date(946684799),"1999-12-31");
+    assert_eq!(date(946684800),"2000-01-01");
+    assert_eq!(date(1677628799),"2023-02-28");
+    assert_eq!(date(1677628800),"2023-03-01");
+    // leap years, 2000 is one but 2100 isn't
+    assert_eq!(date(951782400),"2000-02-29");
+    assert_eq!(date(951868800),"2000-03-01");
+    assert_eq!(date(1709164800),"2024-02-29");
+    assert_eq!(date(4107456000),"2100-02-28");
+    assert_eq!(date(4107542400),"2100-03-01");
+  }
+}
@@ -0,0 +1,74 @@
+//! running independent jobs, like compiling the dependencies, on a thread pool.
+//!
+//! the messages of each job are held back and shown in the order the jobs were given, and a job that
+//! panics is reported instead of going missing. Jobs started from inside a job go on the pool of the next
+//! level, so a job only ever waits on jobs of a deeper level and the waiting can't use up the threads
+//! that the jobs it waits on need.
+
+use ansi_term::Colour::Red;
+use threadpool::ThreadPool;
+
+use std::panic;
+use std::cell::Cell;
+use std::sync::{mpsc,Mutex};
+use std::collections::HashMap;
+
+use messages::{self,Message};
+use processing;
+
+thread_local! {
+  // how deeply the job running on this thread is nested, 0 outside of the jobs
+  static DEPTH : Cell<usize> = const { Cell::new(0) };
+}
+
+// the pools shared by every run, one for each level of nesting
+static POOLS : Mutex<Vec<ThreadPool>> = Mutex::new(Vec::new());
+
+fn pool(depth : usize, count : usize) -> ThreadPool {
+  //! the pool for the jobs at `depth`, made the first time that level is used.
+  let mut pools = POOLS.lock().unwrap_or_else(|error| error.into_inner());
+  while pools.len() <= depth { pools.push(ThreadPool::new(count)); }
+  if pools[depth].max_count() != count { pools[depth].set_num_threads(count); }
+  pools[depth].clone()
+}
+
+pub fn run<T,F>(jobs : Vec<(String,F)>, count : Option<usize>) -> Vec<Option<T>> where T : Send + 'static, F : FnOnce() -> T + Send + 'static {
+  //! runs the jobs and returns their results in the same order, `None` for a job that didn't finish. `count`
+  //! is how many run at the same time, from `--jobs`.
+
+  let depth : usize = DEPTH.with(|depth| depth.get());
+  let pool = pool(depth,processing::gen::job_count(count));
+  let (sender, receiver) = mpsc::channel();
+
+  let mut names : Vec<String> = Vec::new();
+  for (index,(name,job)) in jobs.into_iter().enumerate() {
+    names.push(name);
+    let sender = sender.clone();
+    pool.execute(move || {
+      DEPTH.with(|level| level.set(depth + 1));
+      messages::hold();
+      let result : Option<T> = panic::catch_unwind(panic::AssertUnwindSafe(job)).ok();
+      let _ = sender.send((index,result,messages::release()));
+    });
+  }
+  drop(sender);
+
+  let mut finished : HashMap<usize,(Option<T>,Vec<Message>)> = receiver.iter().map(|(index,result,held)| (index,(result,held))).collect();
+  if finished.len() < names.len() { output_debug!("{} of {} jobs didn't send anything back",names.len() - finished.len(),names.len()); }
+
+  let mut results : Vec<Option<T>> = Vec::new();
+  for (index,name) in names.iter().enumerate() {
+    match finished.remove(&index) {
+      None => {
+        output_error!("{} didn't finish.",Red.paint(name.clone()));
+        results.push(None);
+      }
+      Some((result,held)) => {
+        messages::show(held);
+        if result.is_none() { output_error!("{} stopped with an unexpected error.",Red.paint(name.clone())); }
+        results.push(result);
+      }
+    }
+  }
+  results
+}
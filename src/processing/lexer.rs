@@ -0,0 +1,246 @@
+//! a small lua lexer, only does enough to know what is code, what is a string and what is a comment.
+//!
+//! used to find `@references` and anything else that needs to look at the source without being fooled
+//! by things inside of strings and comments.
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum TokenKind {
+  Name,
+  Keyword,
+  Number,
+  String,
+  Comment,
+  Symbol,
+  Reference,
+}
+
+#[derive(Clone,Debug)]
+pub struct Token {
+  pub kind : TokenKind,
+  pub text : String,
+  pub line : usize,
+  pub start : usize,
+  pub end : usize,
+}
+
+#[derive(Debug)]
+pub struct LexError {
+  pub line : usize,
+  pub message : String,
+}
+
+static KEYWORDS : [&str; 22] = [
+  "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if",
+  "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// longest first, so `...` is found before `..` and `.`
+static SYMBOLS : [&str; 10] = [ "...", "..", "==", "~=", "<=", ">=", "<<", ">>", "//", "::" ];
+
+pub fn tokenize(source : &str) -> Result<Vec<Token>,LexError> {
+  let chars : Vec<(usize,char)> = source.char_indices().collect();
+  let mut tokens : Vec<Token> = Vec::new();
+  let mut line : usize = 1;
+  let mut i : usize = 0;
+
+  // the byte position of the character at `index`
+  let position = |index : usize| -> usize { if index < chars.len() { chars[index].0 } else { source.len() } };
+
+  while i < chars.len() {
+    let c : char = chars[i].1;
+    let start : usize = i;
+    let start_line : usize = line;
+
+    let kind : TokenKind = if c == '\n' { line += 1; i += 1; continue; }
+    else if c.is_whitespace() { i += 1; continue; }
+
+    // comments
+    else if c == '-' && next_is(&chars,i,'-') {
+      i += 2;
+      if let Some(level) = long_bracket_level(&chars,i) {
+        i = skip_long_bracket(&chars,i,level,&mut line).ok_or(LexError { line : start_line, message : "unfinished long comment".to_string() })?;
+      } else {
+        while i < chars.len() && chars[i].1 != '\n' { i += 1; }
+      }
+      TokenKind::Comment
+    }
+
+    // strings
+    else if c == '"' || c == '\'' {
+      i += 1;
+      loop {
+        if i >= chars.len() || chars[i].1 == '\n' { return Err(LexError { line : start_line, message : "unfinished string".to_string() }); }
+        if chars[i].1 == '\\' {
+          i += 1;
+          if i < chars.len() && chars[i].1 == '\n' { line += 1; }
+          // `\z` skips all the whitespace after it, including new lines
+          if i < chars.len() && chars[i].1 == 'z' {
+            while i + 1 < chars.len() && chars[i + 1].1.is_whitespace() { if chars[i + 1].1 == '\n' { line += 1; } i += 1; }
+          }
+        } else if chars[i].1 == c { i += 1; break; }
+        i += 1;
+      }
+      TokenKind::String
+    }
+    else if c == '[' && long_bracket_level(&chars,i).is_some() {
+      let level : usize = long_bracket_level(&chars,i).unwrap();
+      i = skip_long_bracket(&chars,i,level,&mut line).ok_or(LexError { line : start_line, message : "unfinished long string".to_string() })?;
+      TokenKind::String
+    }
+
+    // numbers
+    else if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i + 1].1.is_ascii_digit()) {
+      let hex : bool = c == '0' && i + 1 < chars.len() && (chars[i + 1].1 == 'x' || chars[i + 1].1 == 'X');
+      i += 1;
+      while i < chars.len() {
+        let n : char = chars[i].1;
+        // a sign only belongs to the number right after the exponent
+        let sign : bool = (n == '+' || n == '-') && exponent_before(&chars,i,hex);
+        if n.is_alphanumeric() || n == '.' || n == '_' || sign { i += 1; }
+        else { break; }
+      }
+      TokenKind::Number
+    }
+
+    // names and keywords
+    else if is_name_start(c) {
+      while i < chars.len() && is_name_char(chars[i].1) { i += 1; }
+      let text : &str = &source[position(start)..position(i)];
+      if KEYWORDS.contains(&text) { TokenKind::Keyword } else { TokenKind::Name }
+    }
+
+    // references, `@name.path`, `@self.path`, `@.relative` and `@dep:name`
+    else if c == '@' {
+      i += 1;
+      if i < chars.len() && is_name_start(chars[i].1) {
+        let name_start : usize = i;
+        while i < chars.len() && is_name_char(chars[i].1) { i += 1; }
+        if &source[position(name_start)..position(i)] == "dep" && next_is(&chars,i - 1,':') && i + 1 < chars.len() && is_name_start(chars[i + 1].1) {
+          i += 1;
+          while i < chars.len() && is_name_char(chars[i].1) { i += 1; }
+        }
+      }
+      // the path, each part is a `.name`
+      while i + 1 < chars.len() && chars[i].1 == '.' && is_name_start(chars[i + 1].1) {
+        i += 1;
+        while i < chars.len() && is_name_char(chars[i].1) { i += 1; }
+      }
+      TokenKind::Reference
+    }
+
+    // everything else
+    else {
+      let rest : &str = &source[position(i)..];
+      let length : usize = if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) { symbol.len() } else { 1 };
+      i += length;
+      TokenKind::Symbol
+    };
+
+    tokens.push(Token {
+      kind,
+      text : source[position(start)..position(i)].to_string(),
+      line : start_line,
+      start : position(start),
+      end : position(i),
+    });
+  }
+
+  Ok(tokens)
+}
+
+pub fn is_name_start(c : char) -> bool { c.is_ascii_alphabetic() || c == '_' }
+pub fn is_name_char(c : char) -> bool { c.is_ascii_alphanumeric() || c == '_' }
+
+fn next_is(chars : &[(usize,char)], i : usize, c : char) -> bool {
+  i + 1 < chars.len() && chars[i + 1].1 == c
+}
+
+fn exponent_before(chars : &[(usize,char)], i : usize, hex : bool) -> bool {
+  let previous : char = chars[i - 1].1;
+  if hex { previous == 'p' || previous == 'P' } else { previous == 'e' || previous == 'E' }
+}
+
+fn long_bracket_level(chars : &[(usize,char)], i : usize) -> Option<usize> {
+  //! if there is a long bracket `[==[` at `i`, returns how many `=` it has.
+  if i >= chars.len() || chars[i].1 != '[' { return None; }
+  let mut level : usize = 0;
+  while i + 1 + level < chars.len() && chars[i + 1 + level].1 == '=' { level += 1; }
+  if i + 1 + level < chars.len() && chars[i + 1 + level].1 == '[' { Some(level) } else { None }
+}
+
+fn skip_long_bracket(chars : &[(usize,char)], i : usize, level : usize, line : &mut usize) -> Option<usize> {
+  //! returns the index right after the closing bracket.
+  let mut i : usize = i + level + 2;
+  while i < chars.len() {
+    if chars[i].1 == '\n' { *line += 1; }
+    if chars[i].1 == ']' {
+      let mut count : usize = 0;
+      while i + 1 + count < chars.len() && chars[i + 1 + count].1 == '=' { count += 1; }
+      if count == level && i + 1 + count < chars.len() && chars[i + 1 + count].1 == ']' { return Some(i + count + 2); }
+    }
+    i += 1;
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{tokenize,TokenKind};
+
+  fn kinds(source : &str) -> Vec<(TokenKind,String)> {
+    tokenize(source).unwrap().into_iter().map(|token| (token.kind,token.text)).collect()
+  }
+
+  #[test]
+  fn long_brackets() {
+    let tokens = kinds("local s = [==[ has ]] and ]=] in it ]==] --[[ a\ncomment ]] x");
+    assert_eq!(tokens[3],(TokenKind::String,"[==[ has ]] and ]=] in it ]==]".to_string()));
+    assert_eq!(tokens[4],(TokenKind::Comment,"--[[ a\ncomment ]]".to_string()));
+    assert_eq!(tokens[5],(TokenKind::Name,"x".to_string()));
+
+    // `[=` without a second bracket is just a symbol
+    assert_eq!(kinds("t[=")[1],(TokenKind::Symbol,"[".to_string()));
+    assert!(tokenize("s = [[ never closed").is_err());
+  }
+
+  #[test]
+  fn lines_are_counted_inside_strings_and_comments() {
+    let tokens = tokenize("a = [[\n\n]]\n--[==[\n]==]\nb = \"one \\z\n    two\"\nc").unwrap();
+    let lines : Vec<(String,usize)> = tokens.iter().map(|token| (token.text.clone(),token.line)).collect();
+    assert_eq!(lines[0],("a".to_string(),1));
+    assert_eq!(lines[3].1,4);
+    assert_eq!(lines[4],("b".to_string(),6));
+    assert_eq!(lines.last().unwrap(),&("c".to_string(),8));
+  }
+
+  #[test]
+  fn skip_whitespace_escape() {
+    let tokens = kinds("x = \"a\\z\n\n   b\" y");
+    assert_eq!(tokens[2],(TokenKind::String,"\"a\\z\n\n   b\"".to_string()));
+    assert_eq!(tokens[3],(TokenKind::Name,"y".to_string()));
+    // without the escape a new line ends the string early
+    assert!(tokenize("x = \"a\n b\"").is_err());
+  }
+
+  #[test]
+  fn references() {
+    let tokens = kinds("@dep:json.decode(s) @self.tools @.color @old.path @dep");
+    let references : Vec<&str> = tokens.iter().filter(|&&(kind,_)| kind == TokenKind::Reference).map(|(_,text)| text.as_str()).collect();
+    assert_eq!(references,vec!["@dep:json.decode","@self.tools","@.color","@old.path","@dep"]);
+
+    // not inside strings or comments
+    assert!(kinds("s = \"@self.tools\" -- @dep:json").iter().all(|&(kind,_)| kind != TokenKind::Reference));
+  }
+
+  #[test]
+  fn numbers() {
+    for number in ["3", "3.0", ".5", "1e10", "1E-3", "2.5e+8", "0xff", "0xA.8p-2", "0x1P+4"].iter() {
+      assert_eq!(kinds(number),vec![(TokenKind::Number,number.to_string())],"{}",number);
+    }
+
+    // `-` is only part of the number right after an exponent
+    assert_eq!(kinds("1-2").len(),3);
+    assert_eq!(kinds("0xe-1").len(),3);
+    assert_eq!(kinds("a..1")[1],(TokenKind::Symbol,"..".to_string()));
+  }
+}
@@ -1,3 +1,13 @@
 pub mod buffer;
+pub mod build;
 pub mod compile;
-pub mod gen;
\ No newline at end of file
+pub mod defines;
+pub mod gen;
+pub mod header;
+pub mod jobs;
+pub mod lexer;
+pub mod notices;
+pub mod order;
+pub mod references;
+pub mod settings;
+pub mod tree;
\ No newline at end of file
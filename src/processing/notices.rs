@@ -0,0 +1,157 @@
+//! license notices for the libraries that end up inside a compiled library or a project.
+//!
+//! the license comes from the `license` field of the library's definition, the text from a `LICENSE`
+//! (or `COPYING`) file in the library's folder.
+
+use ansi_term::Colour::{Yellow,Blue};
+
+use std::path::{Path,PathBuf};
+
+use library;
+use library::lualibdef::LibraryDefinition;
+use processing::compile::ResolvedDependency;
+
+static LICENSE_FILES : [&str; 6] = [ "LICENSE", "LICENSE.md", "LICENSE.txt", "COPYING", "COPYING.md", "COPYING.txt" ];
+
+pub struct Notice {
+  pub name : String,
+  pub version : String,
+  pub license : Option<String>,
+  pub text : Option<String>,
+}
+
+pub fn from_library(path : &Path, definition : &LibraryDefinition) -> Notice {
+  Notice {
+    name : definition.name.clone(),
+    version : definition.version.to_string(),
+    license : definition.license.clone(),
+    text : license_text(path),
+  }
+}
+
+pub fn dependencies(resolved : &[ResolvedDependency]) -> Vec<Notice> {
+  //! the notices of every dependency that was compiled into the library, and the ones they depend on.
+  let mut notices : Vec<Notice> = Vec::new();
+  for dependency in resolved.iter() { collect(dependency,&mut notices); }
+  notices.sort_by(|a,b| (&a.name,&a.version).cmp(&(&b.name,&b.version)));
+  notices
+}
+
+fn collect(dependency : &ResolvedDependency, notices : &mut Vec<Notice>) {
+  if let Some(definition) = library::luafile::get_lualib_settings(&dependency.path) {
+    if notices.iter().any(|notice| notice.name == definition.name && notice.version == definition.version.to_string()) { return; }
+
+    let notice = from_library(&dependency.path,&definition);
+    if notice.license.is_none() && notice.text.is_none() { output_warning!("No license found for {} ({})",Blue.paint(notice.name.clone()),Yellow.paint(notice.version.clone())); }
+    notices.push(notice);
+  }
+
+  for dependency in dependency.dependencies.iter() { collect(dependency,notices); }
+}
+
+fn license_text(path : &Path) -> Option<String> {
+  for file in LICENSE_FILES.iter() {
+    let mut license_path : PathBuf = path.to_path_buf();
+    license_path.push(file);
+    if license_path.exists() {
+      let text : String = library::luafile::get_raw_file_contents(&license_path);
+      if !text.trim().is_empty() { return Some(text.trim().to_string()); }
+    }
+  }
+  None
+}
+
+pub fn to_header(notices : &[Notice]) -> String {
+  //! the comment block for the compiled library, the SPDX license if there is one, otherwise the license text.
+  if notices.is_empty() { return String::new(); }
+
+  let mut header : String = "-- includes\n".to_string();
+  for notice in notices.iter() {
+    header = format!("{}--   {} {}:",header,&notice.name,&notice.version);
+    match (&notice.license,&notice.text) {
+      (Some(license),_) => { header = format!("{} {}\n",header,license); }
+      (&None,Some(text)) => {
+        header = format!("{}\n",header);
+        for line in text.lines() { header = format!("{}--     {}\n",header,line); }
+      }
+      (&None,&None) => { header = format!("{} unknown license\n",header); }
+    }
+  }
+  header
+}
+
+pub fn to_file(notices : &[Notice]) -> String {
+  //! the contents of a `THIRD_PARTY_NOTICES` file, with the full license text when there is one.
+  let mut text : String = "This project includes the following libraries.\n".to_string();
+  for notice in notices.iter() {
+    text = format!("{}\n{}\n\n{} {}\n",text,"-".repeat(72),&notice.name,&notice.version);
+    if let Some(ref license) = notice.license { text = format!("{}License: {}\n",text,license); }
+    match notice.text {
+      Some(ref license_text) => { text = format!("{}\n{}\n",text,license_text); }
+      None => { if notice.license.is_none() { text = format!("{}License: unknown\n",text); } }
+    }
+  }
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env;
+  use std::fs;
+  use std::path::{Path,PathBuf};
+  use std::process;
+
+  use processing::compile::ResolvedDependency;
+  use super::{Notice,dependencies,to_file,to_header};
+
+  fn notice(name : &str, license : Option<&str>, text : Option<&str>) -> Notice {
+    Notice { name : name.to_string(), version : "1.0.0".to_string(), license : license.map(|license| license.to_string()), text : text.map(|text| text.to_string()) }
+  }
+
+  fn library(root : &Path, name : &str, license : Option<&str>) -> PathBuf {
+    let path : PathBuf = root.join(name);
+    fs::create_dir_all(&path).unwrap();
+    let license : String = license.map(|license| format!("license = '{}'\n",license)).unwrap_or_default();
+    fs::write(path.join("lib.toml"),format!("name = '{}'\nuser = 'someone'\nauthor = 'someone'\nversion = '1.0.0'\n{}",name,license)).unwrap();
+    path
+  }
+
+  fn resolved(path : &Path, dependencies : Vec<ResolvedDependency>) -> ResolvedDependency {
+    ResolvedDependency { name : path.file_name().unwrap().to_string_lossy().to_string(), version : "1.0.0".to_string(), path : path.to_path_buf(), dependencies }
+  }
+
+  #[test]
+  fn header() {
+    assert_eq!(to_header(&[]),"");
+    let notices : Vec<Notice> = vec![notice("a",Some("MIT"),Some("ignored")),notice("b",None,Some("line one\nline two")),notice("c",None,None)];
+    assert_eq!(to_header(&notices),"-- includes\n--   a 1.0.0: MIT\n--   b 1.0.0:\n--     line one\n--     line two\n--   c 1.0.0: unknown license\n");
+  }
+
+  #[test]
+  fn file() {
+    let notices : Vec<Notice> = vec![notice("a",Some("MIT"),Some("the text")),notice("b",Some("Zlib"),None),notice("c",None,None)];
+    let line : String = "-".repeat(72);
+    assert_eq!(to_file(&notices),format!("This project includes the following libraries.\n\
+      \n{line}\n\na 1.0.0\nLicense: MIT\n\nthe text\n\
+      \n{line}\n\nb 1.0.0\nLicense: Zlib\n\
+      \n{line}\n\nc 1.0.0\nLicense: unknown\n",line = line));
+  }
+
+  #[test]
+  fn transitive_dependencies_once() {
+    let root : PathBuf = env::temp_dir().join(format!("lmake-notices-{}",process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let shared : PathBuf = library(&root,"shared",Some("MIT"));
+    let first : PathBuf = library(&root,"first",Some("Zlib"));
+    let second : PathBuf = library(&root,"second",None);
+    fs::write(second.join("LICENSE"),"  the license text\n\n").unwrap();
+
+    // both depend on `shared`, which only shows up once
+    let found : Vec<Notice> = dependencies(&[resolved(&second,vec![resolved(&shared,Vec::new())]),resolved(&first,vec![resolved(&shared,Vec::new())])]);
+    assert_eq!(found.iter().map(|notice| notice.name.as_str()).collect::<Vec<&str>>(),vec!["first","second","shared"]);
+    assert_eq!(found[1].text,Some("the license text".to_string()));
+    assert_eq!(found[2].license,Some("MIT".to_string()));
+
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
@@ -0,0 +1,102 @@
+//! ordering of the internal modules, based on which modules reference which.
+
+use std::collections::HashMap;
+
+pub fn sort(files : &[String], references : &HashMap<String,Vec<String>>) -> Vec<String> {
+  //! orders the files so every module comes after the modules it references.
+  //!
+  //! always gives the same order for the same files, modules that don't depend on each other are
+  //! in alphabetical order. Cycles don't stop the sort, the first module found in the cycle goes first.
+  let mut sorted_files : Vec<String> = files.to_vec();
+  sorted_files.sort();
+
+  let mut ordered : Vec<String> = Vec::new();
+  let mut visiting : Vec<String> = Vec::new();
+  for file in sorted_files.iter() { visit(file,references,&mut visiting,&mut ordered); }
+  ordered
+}
+
+fn visit(file : &String, references : &HashMap<String,Vec<String>>, visiting : &mut Vec<String>, ordered : &mut Vec<String>) {
+  if ordered.contains(file) || visiting.contains(file) { return; }
+  visiting.push(file.clone());
+
+  if let Some(referenced) = references.get(file) {
+    let mut referenced : Vec<&String> = referenced.iter().collect();
+    referenced.sort();
+    for next in referenced { visit(next,references,visiting,ordered); }
+  }
+
+  visiting.pop();
+  ordered.push(file.clone());
+}
+
+pub fn find_cycles(files : &[String], references : &HashMap<String,Vec<String>>) -> Vec<Vec<String>> {
+  //! finds the reference cycles, each one is the chain of files starting and ending with the same file.
+  let mut sorted_files : Vec<String> = files.to_vec();
+  sorted_files.sort();
+
+  let mut cycles : Vec<Vec<String>> = Vec::new();
+  let mut done : Vec<String> = Vec::new();
+  for file in sorted_files.iter() {
+    let mut chain : Vec<String> = Vec::new();
+    walk(file,references,&mut chain,&mut done,&mut cycles);
+  }
+  cycles
+}
+
+fn walk(file : &String, references : &HashMap<String,Vec<String>>, chain : &mut Vec<String>, done : &mut Vec<String>, cycles : &mut Vec<Vec<String>>) {
+  if let Some(position) = chain.iter().position(|link| link == file) {
+    let mut cycle : Vec<String> = chain[position..].to_vec();
+    cycle.push(file.clone());
+    cycles.push(cycle);
+    return;
+  }
+  if done.contains(file) { return; }
+
+  chain.push(file.clone());
+  if let Some(referenced) = references.get(file) {
+    let mut referenced : Vec<&String> = referenced.iter().collect();
+    referenced.sort();
+    for next in referenced { walk(next,references,chain,done,cycles); }
+  }
+  chain.pop();
+  done.push(file.clone());
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use super::{sort,find_cycles};
+
+  fn graph(edges : &[(&str,&[&str])]) -> (Vec<String>,HashMap<String,Vec<String>>) {
+    let files : Vec<String> = edges.iter().map(|&(file,_)| file.to_string()).collect();
+    let references : HashMap<String,Vec<String>> = edges.iter().map(|&(file,referenced)| (file.to_string(),referenced.iter().map(|r| r.to_string()).collect())).collect();
+    (files,references)
+  }
+
+  #[test]
+  fn sorts_after_references() {
+    let (files,references) = graph(&[("main",&["ui","util"]),("ui",&["util"]),("util",&[]),("assets",&[])]);
+    assert_eq!(sort(&files,&references),vec!["assets","util","ui","main"]);
+    assert!(find_cycles(&files,&references).is_empty());
+  }
+
+  #[test]
+  fn same_order_every_time() {
+    let (mut files,references) = graph(&[("c",&[]),("b",&[]),("a",&["c"])]);
+    let first : Vec<String> = sort(&files,&references);
+    files.reverse();
+    assert_eq!(sort(&files,&references),first);
+  }
+
+  #[test]
+  fn cycles() {
+    let (files,references) = graph(&[("a",&["b"]),("b",&["c"]),("c",&["a"]),("d",&["d"]),("e",&["a"])]);
+
+    // every file is still there once
+    let sorted : Vec<String> = sort(&files,&references);
+    assert_eq!(sorted,vec!["c","b","a","d","e"]);
+
+    assert_eq!(find_cycles(&files,&references),vec![vec!["a","b","c","a"],vec!["d","d"]]);
+  }
+}
@@ -0,0 +1,362 @@
+//! resolves `@references` in the library's source files.
+//!
+//! the syntax is
+//!
+//! - `@dep:name` the dependency called `name` in the `[dependencies]` section.
+//! - `@self.path` the require or internal module with the key `path`, `@self` by itself is the `_` module.
+//! - `@.path` a module next to the current one, so inside `graphics.draw` the reference `@.color` is `graphics.color`.
+//! - `@path` the older form, it is a dependency if the first part is a dependency name, otherwise it is a module.
+//!
+//! anything after the module path is left as is, so `@self.tools.split(text)` becomes `require("..").split(text)`
+//! when `tools` is the module.
+
+use ansi_term::Colour::{Red,Yellow,Blue};
+
+use std::path::{Path,PathBuf};
+use std::collections::HashMap;
+use std::fmt;
+
+use library::lualibdef::LibraryDefinition;
+use processing::lexer::{self,TokenKind,LexError};
+
+pub struct UnresolvedReference {
+  pub reference : String,
+  pub file : PathBuf,
+  pub line : usize,
+  pub suggestion : Option<String>,
+}
+
+impl fmt::Display for UnresolvedReference {
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+    let location : String = format!("{}:{}",self.file.display(),self.line);
+    let mut text : String = format!("Unresolved reference {} in {}",Red.paint(format!("@{}",self.reference)),Yellow.paint(location));
+    if let Some(ref suggestion) = self.suggestion { text = format!("{}, did you mean {}?",text,Blue.paint(format!("@{}",suggestion))); }
+    write!(f,"{}",text)
+  }
+}
+
+pub struct Resolved {
+  pub source : String,
+  pub modules : Vec<String>,
+  pub load_modules : Vec<String>,
+  // the names of the dependencies that were referenced
+  pub dependencies : Vec<String>,
+  pub unresolved : Vec<UnresolvedReference>,
+}
+
+enum Target {
+  Dependency(String),
+  Module(String),
+}
+
+pub struct Resolver<'a> {
+  definition : &'a LibraryDefinition,
+  references : HashMap<String,String>,
+  preload_hash : &'a HashMap<String,String>,
+}
+
+impl<'a> Resolver<'a> {
+  pub fn new(definition : &'a LibraryDefinition, preload_hash : &'a HashMap<String,String>) -> Resolver<'a> {
+    Resolver {
+      definition,
+      references : definition.references().unwrap_or_default(),
+      preload_hash,
+    }
+  }
+
+  pub fn module_key(&self, file : &str) -> Option<String> {
+    //! the key a file is known by, if there is more than one the first one alphabetically.
+    let mut keys : Vec<&String> = self.references.iter().filter(|&(_,value)| value == file).map(|(key,_)| key).collect();
+    keys.sort();
+    keys.first().map(|key| key.to_string())
+  }
+
+  pub fn resolve(&self, contents : &str, src_path : &Path, file : &str) -> Result<Resolved,LexError> {
+    //! replaces all the references in the source of `file` with the `require` of their preload.
+    //!
+    //! also returns the files of the modules and the dependencies that were referenced, and every reference that couldn't be found.
+    let tokens = lexer::tokenize(contents)?;
+    let current_key : Option<String> = self.module_key(file);
+
+    let mut resolved = Resolved { source : String::new(), modules : Vec::new(), load_modules : Vec::new(), dependencies : Vec::new(), unresolved : Vec::new() };
+    let mut last : usize = 0;
+
+    // the open blocks, to know if a reference is inside a function or is required when the file is loaded.
+    let mut blocks : Vec<&str> = Vec::new();
+
+    for token in tokens.iter() {
+      if token.kind == TokenKind::Keyword {
+        match token.text.as_str() {
+          "function" | "if" | "do" | "repeat" => { blocks.push(&token.text); }
+          "end" | "until" => { blocks.pop(); }
+          _ => { }
+        }
+      }
+      if token.kind != TokenKind::Reference { continue; }
+
+      let reference : &str = &token.text[1..];
+
+      match self.find(reference,&current_key) {
+        None => {
+          resolved.unresolved.push(UnresolvedReference {
+            reference : reference.to_string(),
+            file : src_path.to_path_buf(),
+            line : token.line,
+            suggestion : self.suggest(reference),
+          });
+        }
+        Some((target,preload,rest)) => {
+          if let Target::Module(ref module_file) = target {
+            if !resolved.modules.contains(module_file) { resolved.modules.push(module_file.clone()); }
+            if !blocks.contains(&"function") && !resolved.load_modules.contains(module_file) { resolved.load_modules.push(module_file.clone()); }
+          }
+          if let Target::Dependency(ref name) = target {
+            output_debug!("replacing dependency reference {} to {}",name,preload);
+            if !resolved.dependencies.contains(name) { resolved.dependencies.push(name.clone()); }
+          }
+
+          // keeps `return@self.tools` from turning into `returnrequire(..)`
+          let spacer : &str = if token.start > 0 && lexer::is_name_char(contents[..token.start].chars().last().unwrap()) { " " } else { "" };
+
+          resolved.source.push_str(&contents[last..token.start]);
+          resolved.source.push_str(&format!("{}require(\"{}\"){}",spacer,preload,rest));
+          last = token.end;
+        }
+      }
+    }
+
+    resolved.source.push_str(&contents[last..]);
+    Ok(resolved)
+  }
+
+  fn find(&self, reference : &str, current_key : &Option<String>) -> Option<(Target,String,String)> {
+    //! finds what the reference points to, returns the target, its preload name and the part of
+    //! the reference that comes after the module.
+
+    if let Some(name) = reference.strip_prefix("dep:") { return self.find_dependency(name); }
+
+    if let Some(relative) = reference.strip_prefix('.') {
+      // relative to the module the reference is in.
+      let parent : String = match *current_key {
+        Some(ref key) if key != "_" => { let mut parts : Vec<&str> = key.split(".").collect(); parts.pop(); parts.join(".") }
+        _ => { String::new() }
+      };
+      let path : String = if !parent.is_empty() { format!("{}{}",parent,reference) } else { relative.to_string() };
+      return self.find_module(&path);
+    }
+
+    if reference == "self" { return self.find_module("_"); }
+    if let Some(module) = reference.strip_prefix("self.") { return self.find_module(module); }
+
+    // the older form, dependencies first
+    if let Some(found) = self.find_dependency(reference) { return Some(found); }
+    self.find_module(reference)
+  }
+
+  fn find_dependency(&self, reference : &str) -> Option<(Target,String,String)> {
+    let name : &str = reference.split(".").next().unwrap();
+    if let Some(ref dependencies) = self.definition.dependencies {
+      if dependencies.contains_key(name) {
+        if let Some(preload) = self.preload_hash.get(name) {
+          return Some((Target::Dependency(name.to_string()),preload.clone(),reference[name.len()..].to_string()));
+        }
+      }
+    }
+    None
+  }
+
+  fn find_module(&self, path : &str) -> Option<(Target,String,String)> {
+    //! goes through the path backwards, `@a.b.func` could be the module `a.b` or `a.b.func`, the longest one wins.
+    let mut parts : Vec<&str> = path.split(".").collect();
+    while !parts.is_empty() {
+      let key : String = parts.join(".");
+      if let Some(file) = self.references.get(&key) {
+        if let Some(preload) = self.preload_hash.get(file) {
+          return Some((Target::Module(file.clone()),preload.clone(),path[key.len()..].to_string()));
+        }
+      }
+      parts.pop();
+    }
+    None
+  }
+
+  fn suggest(&self, reference : &str) -> Option<String> {
+    let mut modules : Vec<String> = self.references.keys().filter(|key| *key != "_").cloned().collect();
+    modules.sort();
+    let mut dependencies : Vec<String> = if let Some(ref dependencies) = self.definition.dependencies { dependencies.keys().cloned().collect() } else { Vec::new() };
+    dependencies.sort();
+
+    if let Some(name) = reference.strip_prefix("dep:") { suggest(name,&dependencies).map(|suggestion| format!("dep:{}",suggestion)) }
+    else if let Some(module) = reference.strip_prefix("self.") { suggest(module,&modules).map(|suggestion| format!("self.{}",suggestion)) }
+    else if reference.starts_with(".") { None }
+    else {
+      if let Some(suggestion) = suggest(reference,&dependencies) { return Some(format!("dep:{}",suggestion)); }
+      suggest(reference,&modules).map(|suggestion| format!("self.{}",suggestion))
+    }
+  }
+}
+
+pub fn report_unresolved(unresolved : &[UnresolvedReference], allowed : bool) -> bool {
+  //! shows all the references that couldn't be resolved. Returns false if there were any, unless
+  //! unresolved references are allowed with `--allow-unresolved`, then they are only warnings.
+  if unresolved.is_empty() { return true; }
+
+  for reference in unresolved.iter() {
+    if allowed { output_warning!("{}",reference.to_string()); }
+    else { output_error!("{}",reference.to_string()); }
+  }
+
+  allowed
+}
+
+fn suggest(reference : &str, candidates : &[String]) -> Option<String> {
+  //! finds the closest candidate, compared against the same number of parts of the reference.
+  let parts : Vec<&str> = reference.split(".").collect();
+  let mut best : Option<(usize,String)> = None;
+
+  for candidate in candidates.iter() {
+    let count : usize = candidate.split(".").count();
+    if count > parts.len() { continue; }
+    let compared : String = parts[..count].join(".");
+    let distance : usize = edit_distance(&compared,candidate);
+
+    // has to be close enough to be a typo
+    if distance == 0 || distance > 2.max(candidate.len() / 3) { continue; }
+
+    let is_better : bool = match best { None => { true } Some((best_distance,_)) => { distance < best_distance } };
+    if is_better {
+      let mut suggestion : Vec<&str> = vec![candidate.as_str()];
+      suggestion.extend_from_slice(&parts[count..]);
+      best = Some((distance,suggestion.join(".")));
+    }
+  }
+
+  best.map(|(_,suggestion)| suggestion)
+}
+
+fn edit_distance(a : &str, b : &str) -> usize {
+  let a : Vec<char> = a.chars().collect();
+  let b : Vec<char> = b.chars().collect();
+  let mut previous : Vec<usize> = (0..b.len()+1).collect();
+
+  for (i,a_char) in a.iter().enumerate() {
+    let mut current : Vec<usize> = vec![i + 1];
+    for (j,b_char) in b.iter().enumerate() {
+      let cost : usize = if a_char == b_char { 0 } else { 1 };
+      current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::path::PathBuf;
+
+  use library::luafile;
+  use library::lualibdef::LibraryDefinition;
+  use processing::compile::require_path;
+  use super::{Resolver,Target};
+
+  static DEFINITION : &str = r#"
+    name = "shapes"
+    user = "someone"
+    author = "someone"
+    version = "1.0.0"
+
+    [requires]
+    _ = "src.shapes"
+    draw = "src.draw"
+    "graphics.color" = "src.graphics.color"
+    "graphics.color.names" = "src.graphics.names"
+
+    [dependencies]
+    json = { version = "1.0.0" }
+  "#;
+
+  fn preloads() -> HashMap<String,String> {
+    let mut preload_hash : HashMap<String,String> = HashMap::new();
+    for &(key,preload) in [
+      ("src.shapes","shapes._"), ("src.draw","shapes.draw"), ("src.graphics.color","shapes.graphics.color"),
+      ("src.graphics.names","shapes.graphics.color.names"), ("json","json.1.0.0"),
+    ].iter() { preload_hash.insert(key.to_string(),preload.to_string()); }
+    preload_hash
+  }
+
+  fn found(resolver : &Resolver, reference : &str, current_key : Option<&str>) -> Option<(String,String)> {
+    //! the preload and the rest of the reference, or `None`.
+    resolver.find(reference,&current_key.map(|key| key.to_string())).map(|(_,preload,rest)| (preload,rest))
+  }
+
+  fn some(preload : &str, rest : &str) -> Option<(String,String)> { Some((preload.to_string(),rest.to_string())) }
+
+  #[test]
+  fn find() {
+    let definition : LibraryDefinition = luafile::parse_lualib_settings(DEFINITION).unwrap();
+    let preload_hash = preloads();
+    let resolver = Resolver::new(&definition,&preload_hash);
+
+    assert_eq!(found(&resolver,"dep:json.decode",None),some("json.1.0.0",".decode"));
+    assert_eq!(found(&resolver,"self",None),some("shapes._",""));
+    assert_eq!(found(&resolver,"self.draw.line",None),some("shapes.draw",".line"));
+    // the longest module wins
+    assert_eq!(found(&resolver,"self.graphics.color.names.red",None),some("shapes.graphics.color.names",".red"));
+    assert_eq!(found(&resolver,"self.graphics.color.mix",None),some("shapes.graphics.color",".mix"));
+
+    // relative to the current module
+    assert_eq!(found(&resolver,".color.names",Some("graphics.color")),some("shapes.graphics.color.names",""));
+    assert_eq!(found(&resolver,".names",Some("graphics.color")),None);
+    assert_eq!(found(&resolver,".draw",Some("_")),some("shapes.draw",""));
+
+    // the older form looks at the dependencies first
+    match resolver.find("json",&None) { Some((Target::Dependency(name),_,_)) => assert_eq!(name,"json"), _ => panic!("json should be the dependency") }
+    assert_eq!(found(&resolver,"draw",None),some("shapes.draw",""));
+
+    assert_eq!(found(&resolver,"dep:draw",None),None);
+    assert_eq!(found(&resolver,"self.json",None),None);
+    assert_eq!(found(&resolver,"self.graphics",None),None);
+  }
+
+  #[test]
+  fn resolve() {
+    let definition : LibraryDefinition = luafile::parse_lualib_settings(DEFINITION).unwrap();
+    let preload_hash = preloads();
+    let resolver = Resolver::new(&definition,&preload_hash);
+
+    let source : &str = "local json = @dep:json\nlocal names = @.color.names\nfunction red() return @json.decode(names.red) end\nlocal text = \"@dep:json\"";
+    let resolved = resolver.resolve(source,&require_path(&PathBuf::from("shapes"),"src.graphics.color"),"src.graphics.color").unwrap();
+
+    assert_eq!(resolved.source,"local json = require(\"json.1.0.0\")\nlocal names = require(\"shapes.graphics.color.names\")\nfunction red() return require(\"json.1.0.0\").decode(names.red) end\nlocal text = \"@dep:json\"");
+    // only once, even with the older form
+    assert_eq!(resolved.dependencies,vec!["json".to_string()]);
+    assert_eq!(resolved.modules,vec!["src.graphics.names".to_string()]);
+    assert!(resolved.unresolved.is_empty());
+
+    let resolved = resolver.resolve("return @self.draw",&require_path(&PathBuf::from("shapes"),"src.shapes"),"src.shapes").unwrap();
+    assert!(resolved.dependencies.is_empty());
+    assert_eq!(resolved.load_modules,vec!["src.draw".to_string()]);
+  }
+
+  #[test]
+  fn suggest() {
+    let definition : LibraryDefinition = luafile::parse_lualib_settings(DEFINITION).unwrap();
+    let preload_hash = preloads();
+    let resolver = Resolver::new(&definition,&preload_hash);
+
+    assert_eq!(resolver.suggest("dep:jsno"),Some("dep:json".to_string()));
+    assert_eq!(resolver.suggest("self.drwa.line"),Some("self.draw.line".to_string()));
+    assert_eq!(resolver.suggest("self.graphics.colr"),Some("self.graphics.color".to_string()));
+    // the older form gets the newer syntax
+    assert_eq!(resolver.suggest("jsn.decode"),Some("dep:json.decode".to_string()));
+    assert_eq!(resolver.suggest("daw"),Some("self.draw".to_string()));
+
+    // too far off, or nothing to compare to
+    assert_eq!(resolver.suggest("self.physics"),None);
+    assert_eq!(resolver.suggest(".drwa"),None);
+    // `_` is never suggested
+    assert_eq!(resolver.suggest("self.a"),None);
+  }
+}
@@ -0,0 +1,115 @@
+//! the options a library is built with.
+//!
+//! each option comes from the command line switches first, then a target's profile, then the profile
+//! and then the lpsettings, so `--minify` is always on even if a profile turns it off.
+
+use lpsettings;
+use toml;
+
+use std::path::Path;
+use std::collections::HashMap;
+
+use library;
+use library::lualibdef::{LibraryDefinition,Profile};
+use processing;
+
+#[derive(Clone)]
+pub struct Settings {
+  // the name of the profile, for the `PROFILE` define
+  pub profile_name : String,
+  // the command line switches, only the ones that were given are set
+  pub switches : Profile,
+  pub profile : Profile,
+  // the `--define KEY=VALUE`s, they go on top of every other define
+  pub command_defines : Vec<String>,
+  pub allow_unresolved : bool,
+  pub deny_globals : bool,
+  // `--jobs`, how many libraries are compiled at the same time
+  pub jobs : Option<usize>,
+}
+
+impl Default for Settings {
+  fn default() -> Settings { Settings::new("dev",Profile::default(),Profile::default()) }
+}
+
+impl Settings {
+  pub fn new(profile_name : &str, switches : Profile, profile : Profile) -> Settings {
+    Settings {
+      profile_name : profile_name.to_string(),
+      switches,
+      profile,
+      command_defines : Vec::new(),
+      allow_unresolved : false,
+      deny_globals : false,
+      jobs : None,
+    }
+  }
+
+  pub fn load(library_path : &Path, profile_name : &str, switches : Profile) -> Result<Settings,String> {
+    //! the settings for the library in `library_path` with the profile called `profile_name`. A folder that
+    //! isn't a library gets the built in profile, compiling it will say what is wrong.
+    let definition : Option<LibraryDefinition> = if processing::compile::validate_lualib_path_quiet(library_path) { library::luafile::get_lualib_settings(library_path) } else { None };
+    let profile : Profile = match definition {
+      None => { Profile::default_for(profile_name).unwrap_or_default() }
+      Some(definition) => { definition.get_profile(profile_name)? }
+    };
+    Ok(Settings::new(profile_name,switches,profile))
+  }
+
+  pub fn with_profile(&self, profile_name : &str, profile : Profile) -> Settings {
+    //! a profile on top of the current one, like the profile of a target.
+    Settings { profile_name : profile_name.to_string(), profile : profile.inherit(self.profile.clone()), .. self.clone() }
+  }
+
+  pub fn for_dependency(&self, definition : &LibraryDefinition) -> Settings {
+    //! a dependency is built the same way, but only gets the defines of its own profile with the same name,
+    //! if it has one.
+    let mut settings : Settings = self.clone();
+    settings.profile.defines = definition.get_profile(&self.profile_name).ok().and_then(|profile| profile.defines);
+    settings
+  }
+
+  pub fn remove_comments(&self) -> bool { self.switch(|profile| profile.remove_comments,"lmake.remove-comments") }
+  pub fn minify(&self) -> bool { self.switch(|profile| profile.minify,"lmake.minify") }
+  pub fn debug_build(&self) -> bool { self.switch(|profile| profile.debug_build,"lmake.debug-build") }
+  pub fn name_with_version(&self) -> bool { self.switch(|profile| profile.name_with_version,"lmake.name-with-version") }
+
+  pub fn compiled_name(&self) -> Option<String> {
+    self.switches.compiled_name.clone().or(self.profile.compiled_name.clone())
+  }
+
+  pub fn output(&self) -> Option<String> { self.profile.output.clone() }
+
+  pub fn defines(&self) -> Option<&HashMap<String,toml::Value>> { self.profile.defines.as_ref() }
+
+  fn switch<F>(&self, get : F, setting : &str) -> bool where F : Fn(&Profile) -> Option<bool> {
+    get(&self.switches).or(get(&self.profile)).unwrap_or(lpsettings::get_value_or(setting,"false") == "true")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use library::lualibdef::Profile;
+  use super::Settings;
+
+  #[test]
+  fn switches_win() {
+    let switches : Profile = Profile { minify : Some(true), .. Profile::default() };
+    let profile : Profile = Profile { minify : Some(true), remove_comments : Some(true), debug_build : Some(true), .. Profile::default() };
+    let settings : Settings = Settings::new("release",switches,profile);
+
+    // a target's profile turning things off
+    let target : Profile = Profile { minify : Some(false), debug_build : Some(false), output : Some("core".to_string()), .. Profile::default() };
+    let target_settings : Settings = settings.with_profile("core",target);
+
+    assert!(target_settings.minify());
+    assert!(!target_settings.debug_build());
+    assert!(target_settings.remove_comments());
+    assert_eq!(target_settings.output(),Some("core".to_string()));
+    assert_eq!(target_settings.profile_name,"core");
+
+    // and the library's settings are the same as before
+    assert!(settings.debug_build());
+    assert_eq!(settings.output(),None);
+  }
+}
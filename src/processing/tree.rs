@@ -0,0 +1,210 @@
+use ansi_term::Colour::{Red,Yellow,Blue};
+use serde_json;
+
+use std::path::{Path,PathBuf};
+use std::collections::HashMap;
+
+use library;
+use library::lualibdef::{LibraryDefinition,Dependency};
+use processing::compile::{get_library_tag,get_library_path_git,get_definition_at_tag,cache_path};
+use version::version::Version;
+
+#[derive(Serialize)]
+pub struct DependencyNode {
+  pub name : String,
+  pub reference : String,
+  pub requested : String,
+  pub resolved : Option<String>,
+  pub source : Option<PathBuf>,
+  pub cache : Option<PathBuf>,
+  pub cached : bool,
+  pub duplicate : bool,
+  pub conflict : bool,
+  pub dependencies : Vec<DependencyNode>,
+}
+
+pub fn build(path : &Path, definition : &LibraryDefinition) -> DependencyNode {
+  //! resolves the full dependency tree for the library at `path`, without cloning anything into the cache.
+  //!
+  //! the same library showing up again with the same resolved version is marked as a duplicate and isn't
+  //! expanded again, the same library resolving to different versions is marked as a conflict everywhere it shows up.
+
+  let mut seen : HashMap<String,Option<String>> = HashMap::new();
+  seen.insert(definition.name.clone(),Some(definition.version.to_string()));
+
+  let mut root = DependencyNode {
+    name : definition.name.clone(),
+    reference : definition.name.clone(),
+    requested : definition.version.to_string(),
+    resolved : Some(definition.version.to_string()),
+    source : Some(path.to_path_buf()),
+    cache : None,
+    cached : true,
+    duplicate : false,
+    conflict : false,
+    dependencies : resolve_dependencies(definition,&mut seen),
+  };
+
+  // marks all the conflicts
+  let mut versions : HashMap<String,Vec<Option<String>>> = HashMap::new();
+  collect_versions(&root,&mut versions);
+  mark_conflicts(&mut root,&versions);
+
+  root
+}
+
+fn resolve_dependencies(definition : &LibraryDefinition, seen : &mut HashMap<String,Option<String>>) -> Vec<DependencyNode> {
+  let mut nodes : Vec<DependencyNode> = Vec::new();
+
+  if let Some(ref hash) = definition.dependencies {
+    let mut names : Vec<&String> = hash.keys().collect();
+    names.sort();
+
+    for name in names {
+      let dependency : &Dependency = &hash[name];
+      let library_name : String = dependency.library_name(name);
+      let requested : String = dependency.version.clone().unwrap_or("*".to_string());
+      let local : Option<PathBuf> = definition.dependency_source(&library_name,dependency);
+
+      let mut node = DependencyNode {
+        name : library_name.clone(),
+        reference : name.clone(),
+        requested : requested.clone(),
+        resolved : None,
+        source : local.clone().or_else(|| get_library_path_git(&library_name)),
+        cache : None,
+        cached : false,
+        duplicate : false,
+        conflict : false,
+        dependencies : Vec::new(),
+      };
+
+      match Version::from_str(&requested) {
+        None => { output_error!("Malformed version requirement in dependency for {}: {}",Blue.paint(library_name.to_string()),Red.paint(requested.to_string())); }
+        Some(version) => {
+          // the matching tag, only looking at the repository so nothing is cloned just to show the tree
+          let tag : Option<String> = if local.is_some() { None } else { get_library_tag(&library_name,&version).map(|(tag,_)| tag) };
+          node.resolved = match local {
+            Some(ref path) => { library::luafile::get_lualib_settings(path).map(|definition| definition.version.to_string()) }
+            None => { tag.as_ref().map(|tag| Version::from_str(tag).map(|version| version.to_string()).unwrap_or(tag.clone())) }
+          };
+
+          let already_seen : bool = match seen.get(&library_name) {
+            None => { false }
+            Some(resolved) => { *resolved == node.resolved }
+          };
+
+          if already_seen { node.duplicate = true; }
+          else {
+            seen.insert(library_name.clone(),node.resolved.clone());
+            node.cache = match (&local,&tag) {
+              (Some(local),_) => { Some(local.clone()) }
+              (&None,Some(tag)) => { Some(cache_path(&library_name,tag)).filter(|path| path.exists()) }
+              (&None,&None) => { None }
+            };
+            node.cached = node.cache.is_some();
+
+            // the definition from the cache, otherwise from the tag in the repository
+            let dependency_definition : Option<LibraryDefinition> = match (&node.cache,&node.source,&tag) {
+              (Some(cache),_,_) => { library::luafile::get_lualib_settings(cache) }
+              (&None,Some(source),Some(tag)) => { get_definition_at_tag(source,tag) }
+              _ => { None }
+            };
+            match dependency_definition {
+              None => { if node.resolved.is_some() { output_error!("Error loading lualib definition file for {}",Yellow.paint(library_name.clone())); } }
+              Some(mut dependency_definition) => {
+                if let Err(error) = dependency_definition.with_features(&dependency.feature_request()) { output_error!("{}",Red.paint(error)); }
+                node.dependencies = resolve_dependencies(&dependency_definition,seen);
+              }
+            }
+          }
+        }
+      }
+
+      nodes.push(node);
+    }
+  }
+
+  nodes
+}
+
+fn collect_versions(node : &DependencyNode, versions : &mut HashMap<String,Vec<Option<String>>>) {
+  {
+    let list = versions.entry(node.name.clone()).or_default();
+    if !list.contains(&node.resolved) { list.push(node.resolved.clone()); }
+  }
+  for dependency in node.dependencies.iter() { collect_versions(dependency,versions); }
+}
+
+fn mark_conflicts(node : &mut DependencyNode, versions : &HashMap<String,Vec<Option<String>>>) {
+  if let Some(list) = versions.get(&node.name) { node.conflict = list.len() > 1; }
+  for dependency in node.dependencies.iter_mut() { mark_conflicts(dependency,versions); }
+}
+
+pub fn to_text(root : &DependencyNode) -> String {
+  //! the tree as lines, like the unix `tree` command.
+  let mut buffer : String = format!("{} {} ({})",Blue.paint(root.name.clone()),Yellow.paint(root.requested.clone()),display_path(&root.source));
+  text_children(&root.dependencies,"",&mut buffer);
+  buffer
+}
+
+fn text_children(nodes : &[DependencyNode], prefix : &str, buffer : &mut String) {
+  for (index,node) in nodes.iter().enumerate() {
+    let last : bool = index == nodes.len() - 1;
+
+    let resolved : String = if let Some(ref resolved) = node.resolved { resolved.clone() } else { "?".to_string() };
+    let mut line : String = format!("{}{} {} {} -> {}",
+      prefix, if last { "└──" } else { "├──" },
+      Blue.paint(node.reference.clone()), node.requested, Yellow.paint(resolved));
+    if node.reference != node.name { line = format!("{} [{}]",line,node.name); }
+    line = format!("{} ({})",line,display_path(if node.cache.is_some() { &node.cache } else { &node.source }));
+    if !node.cached && !node.duplicate && node.resolved.is_some() { line = format!("{} {}",line,Yellow.paint("(not cached)")); }
+    if node.duplicate { line = format!("{} (*)",line); }
+    if node.conflict { line = format!("{} {}",line,Red.paint("(conflict)")); }
+
+    *buffer = format!("{}\n{}",buffer,line);
+    text_children(&node.dependencies,&format!("{}{}",prefix,if last { "    " } else { "│   " }),buffer);
+  }
+}
+
+fn display_path(path : &Option<PathBuf>) -> String {
+  if let Some(ref path) = *path { path.display().to_string() } else { "not found".to_string() }
+}
+
+pub fn to_json(root : &DependencyNode) -> String {
+  match serde_json::to_string_pretty(root) {
+    Err(error) => { output_error!("Error creating json: {}",Red.paint(error.to_string())); String::new() }
+    Ok(json) => { json }
+  }
+}
+
+pub fn to_dot(root : &DependencyNode) -> String {
+  //! the tree as a graphviz digraph, conflicts are colored red.
+  let mut lines : Vec<String> = Vec::new();
+  dot_node(root,&mut lines);
+  dot_edges(root,&mut lines);
+
+  let mut buffer : String = format!("digraph \"{}\" {{",root.name);
+  for line in lines { buffer = format!("{}\n  {}",buffer,line); }
+  format!("{}\n}}",buffer)
+}
+
+fn dot_id(node : &DependencyNode) -> String {
+  format!("\"{} {}\"",node.name,if let Some(ref resolved) = node.resolved { resolved.clone() } else { node.requested.clone() })
+}
+
+fn dot_node(node : &DependencyNode, lines : &mut Vec<String>) {
+  let line : String = format!("{} [label=\"{}\\n{}\"{}];",dot_id(node),node.name,
+    if let Some(ref resolved) = node.resolved { resolved.clone() } else { node.requested.clone() },
+    if node.conflict { ",color=red" } else { "" });
+  if !lines.contains(&line) { lines.push(line); }
+  for dependency in node.dependencies.iter() { dot_node(dependency,lines); }
+}
+
+fn dot_edges(node : &DependencyNode, lines : &mut Vec<String>) {
+  for dependency in node.dependencies.iter() {
+    let line : String = format!("{} -> {} [label=\"{}\"];",dot_id(node),dot_id(dependency),dependency.requested);
+    if !lines.contains(&line) { lines.push(line); }
+    dot_edges(dependency,lines);
+  }
+}